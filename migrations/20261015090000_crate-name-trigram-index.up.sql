@@ -0,0 +1,4 @@
+-- used to find crates with names similar to an unknown one, for suggestions on 404 pages.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX crates_normalized_name_trgm_idx ON crates USING gin (normalize_crate_name(name) gin_trgm_ops);
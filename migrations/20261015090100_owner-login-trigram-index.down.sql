@@ -0,0 +1 @@
+DROP INDEX owners_login_trgm_idx;
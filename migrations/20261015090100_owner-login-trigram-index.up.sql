@@ -0,0 +1,2 @@
+-- used to find owners with logins similar to an unknown one, for suggestions on 404 pages.
+CREATE INDEX owners_login_trgm_idx ON owners USING gin (lower(login) gin_trgm_ops);
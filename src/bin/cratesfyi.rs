@@ -515,10 +515,10 @@ enum DatabaseSubcommand {
     /// Backfill GitHub/Gitlab stats for crates.
     BackfillRepositoryStats,
 
-    /// Updates info for a crate from the registry's API
+    /// Updates info for crates and their releases from the registry's API
     UpdateCrateRegistryFields {
-        #[arg(name = "CRATE")]
-        name: String,
+        #[arg(name = "CRATE", required = true)]
+        names: Vec<String>,
     },
 
     AddDirectory {
@@ -601,10 +601,20 @@ impl DatabaseSubcommand {
                     .block_on(ctx.repository_stats_updater()?.backfill_repositories())?;
             }
 
-            Self::UpdateCrateRegistryFields { name } => ctx.runtime()?.block_on(async move {
+            Self::UpdateCrateRegistryFields { names } => ctx.runtime()?.block_on(async move {
                 let mut conn = ctx.pool()?.get_async().await?;
-                let registry_data = ctx.registry_api()?.get_crate_data(&name).await?;
-                db::update_crate_data_in_database(&mut conn, &name, &registry_data).await
+                let registry_api = ctx.registry_api()?;
+
+                // fetch the crate data of all crates up front, concurrently
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                registry_api.warm_owner_cache(&names).await;
+
+                for name in names {
+                    let registry_data = registry_api.get_crate_data(name).await?;
+                    db::update_crate_data_in_database(&mut conn, name, &registry_data).await?;
+                    db::update_release_data_in_database(&mut conn, &registry_api, name).await?;
+                }
+                Ok::<(), anyhow::Error>(())
             })?,
 
             Self::AddDirectory { directory } => {
@@ -867,7 +877,7 @@ impl Context for BinContext {
         };
         fn registry_api(self) -> RegistryApi = {
             let config = self.config()?;
-            RegistryApi::new(config.registry_api_host.clone(), config.crates_io_api_call_retries)?
+            RegistryApi::new(&config)?.with_metrics(self.instance_metrics()?)
         };
         fn repository_stats_updater(self) -> RepositoryStatsUpdater = {
             let config = self.config()?;
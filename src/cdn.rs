@@ -16,6 +16,7 @@ use std::{
 use strum::EnumString;
 use tokio::runtime::Runtime;
 use tracing::{debug, info, instrument, warn};
+use url::Url;
 use uuid::Uuid;
 
 /// maximum amout of parallel in-progress wildcard invalidations
@@ -440,6 +441,35 @@ pub(crate) fn handle_queued_invalidation_requests(
     Ok(())
 }
 
+/// The surrogate key (CDN cache tag) for all responses about the given crate.
+pub fn crate_surrogate_key(name: &str) -> String {
+    format!("crate:{name}")
+}
+
+/// Build the request to purge all responses tagged with any of the given
+/// surrogate keys from the CDN, using the Fastly purge API.
+pub fn surrogate_key_purge_request(
+    client: &reqwest::Client,
+    api_base: &Url,
+    service_id: &str,
+    api_token: &str,
+    keys: &[String],
+) -> Result<reqwest::Request> {
+    if keys.is_empty() {
+        bail!("no surrogate keys to purge");
+    }
+
+    let url = api_base
+        .join(&format!("service/{service_id}/purge"))
+        .context("invalid CDN purge URL")?;
+
+    Ok(client
+        .post(url)
+        .header("Fastly-Key", api_token)
+        .header("Surrogate-Key", keys.join(" "))
+        .build()?)
+}
+
 #[instrument(skip(conn, config))]
 pub(crate) fn queue_crate_invalidation(
     conn: &mut impl postgres::GenericClient,
@@ -628,6 +658,41 @@ mod tests {
         })
     }
 
+    #[test]
+    fn build_surrogate_key_purge_request() -> Result<()> {
+        let request = surrogate_key_purge_request(
+            &reqwest::Client::new(),
+            &"https://api.fastly.com/".parse()?,
+            "service-id",
+            "secret",
+            &[crate_surrogate_key("tokio"), crate_surrogate_key("serde")],
+        )?;
+
+        assert_eq!(request.method(), reqwest::Method::POST);
+        assert_eq!(
+            request.url().as_str(),
+            "https://api.fastly.com/service/service-id/purge"
+        );
+        assert_eq!(request.headers()["Fastly-Key"], "secret");
+        assert_eq!(
+            request.headers()["Surrogate-Key"],
+            "crate:tokio crate:serde"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn surrogate_key_purge_request_needs_keys() {
+        assert!(surrogate_key_purge_request(
+            &reqwest::Client::new(),
+            &"https://api.fastly.com/".parse().unwrap(),
+            "service-id",
+            "secret",
+            &[],
+        )
+        .is_err());
+    }
+
     #[test]
     fn invalidation_counts_are_zero_with_empty_queue() {
         crate::test::wrapper(|env| {
@@ -1,6 +1,8 @@
-use crate::{cdn::CdnKind, storage::StorageKind};
+use crate::{cdn::CdnKind, registry_api::DEFAULT_API_BASE, storage::StorageKind};
 use anyhow::{anyhow, bail, Context, Result};
-use std::{env::VarError, error::Error, path::PathBuf, str::FromStr, time::Duration};
+use std::{
+    collections::HashMap, env::VarError, error::Error, path::PathBuf, str::FromStr, time::Duration,
+};
 use tracing::trace;
 use url::Url;
 
@@ -10,6 +12,23 @@ pub struct Config {
     pub registry_index_path: PathBuf,
     pub registry_url: Option<String>,
     pub registry_api_host: Url,
+    // don't talk to the registry API at all, not even to the default host.
+    // Crate data is then filled in from the sparse index fallback or left empty.
+    pub(crate) registry_api_offline: bool,
+    // User-Agent sent to the registry API, defaults to the docs.rs name and version
+    pub(crate) registry_api_user_agent: Option<String>,
+    // path of the per-crate endpoints on the registry API, `{name}` is the crate name
+    pub(crate) registry_api_crate_path: String,
+    // proxy for requests to the registry API, `http://`, `https://` or `socks5://`
+    pub(crate) registry_api_proxy: Option<Url>,
+    // comma-separated hosts that are reached without the proxy, like `NO_PROXY`
+    pub(crate) registry_api_no_proxy: Option<String>,
+    // sparse index we read release data from when the registry API fails.
+    // The fallback is disabled when this isn't set.
+    pub(crate) registry_sparse_index_fallback: Option<Url>,
+    // prefix for owner avatar URLs, so browsers load them through our image proxy.
+    // The original URL is appended percent-encoded.
+    pub(crate) registry_avatar_proxy: Option<String>,
 
     // Database connection params
     pub(crate) database_url: String,
@@ -43,11 +62,56 @@ pub struct Config {
 
     // amount of retries for external API calls, mostly crates.io
     pub crates_io_api_call_retries: u32,
+    // base delay before retrying a crates.io API call, doubled on every attempt
+    pub(crate) crates_io_api_call_retry_delay: Duration,
+    // longest we wait before retrying, also when the registry asks for more in `Retry-After`
+    pub(crate) crates_io_api_max_retry_delay: Duration,
+    // timeout for a single crates.io API call
+    pub(crate) crates_io_api_call_timeout: Duration,
+    // how long crate data fetched from crates.io is reused, and for how many crates
+    pub(crate) crates_io_crate_data_cache_ttl: Duration,
+    pub(crate) crates_io_crate_data_cache_size: usize,
+    // how many crates.io API responses to remember for conditional requests
+    pub(crate) crates_io_etag_cache_size: usize,
+    // how many crates.io API calls may be in flight at the same time,
+    // and the minimum time between starting two of them
+    pub(crate) crates_io_api_max_concurrent_requests: usize,
+    pub(crate) crates_io_api_min_request_interval: Duration,
+    // after this many failed crates.io API calls in a row, we stop calling it
+    // for the cooldown. 0 disables this.
+    pub(crate) crates_io_api_circuit_breaker_threshold: u32,
+    pub(crate) crates_io_api_circuit_breaker_cooldown: Duration,
+    // how many idle connections to crates.io are kept per host, and for how long.
+    // Unset, the defaults of the HTTP client apply.
+    pub(crate) crates_io_api_pool_max_idle_per_host: Option<usize>,
+    pub(crate) crates_io_api_pool_idle_timeout: Option<Duration>,
+    // only every n-th successful crates.io API call per endpoint is logged,
+    // failures are always logged. 0 disables logging successful calls.
+    pub(crate) crates_io_api_log_sample_rate: u32,
+    // when set, crates.io API responses are cached on disk in this directory,
+    // for local development & CI. Not meant for production.
+    pub(crate) crates_io_api_disk_cache_path: Option<PathBuf>,
+    pub(crate) crates_io_api_disk_cache_ttl: Duration,
+    // version lists of the registry API with more versions are rejected,
+    // so a crate with a pathological number of versions can't exhaust our memory.
+    pub(crate) crates_io_max_versions: usize,
+    // registry API responses larger than this many bytes are rejected before
+    // we parse them, for the same reason.
+    pub(crate) crates_io_max_response_size: usize,
 
     // request timeout in seconds
     pub(crate) request_timeout: Option<Duration>,
     pub(crate) report_request_timeouts: bool,
 
+    // how many requests per second the expensive endpoints, like the source
+    // browser, accept for a single crate, and how many more in a burst.
+    // Unset, they aren't limited.
+    pub(crate) crate_rate_limit: Option<f64>,
+    pub(crate) crate_rate_limit_burst: u32,
+
+    // Max size of request bodies we accept, in bytes
+    pub(crate) max_request_body_size: usize,
+
     // Max size of the files served by the docs.rs frontend
     pub(crate) max_file_size: usize,
     pub(crate) max_file_size_html: usize,
@@ -68,6 +132,12 @@ pub struct Config {
     // For unit-tests the number has to be higher.
     pub(crate) random_crate_search_view_size: u32,
 
+    // when set, search results sorted by downloads are ranked by a blend of
+    // recent and all-time downloads, where older downloads lose half their
+    // weight every half-life. This only re-ranks the crates within each
+    // result page, the pages themselves are still cut by all-time downloads.
+    pub(crate) search_popularity_half_life: Option<Duration>,
+
     // where do we want to store the locally cached index files
     // for the remote archives?
     pub(crate) local_archive_cache_path: PathBuf,
@@ -80,6 +150,10 @@ pub struct Config {
     // generate just that directive. Values are in seconds.
     pub(crate) cache_control_stale_while_revalidate: Option<u32>,
 
+    // Cache-Control max-age of redirects from version requirements to the
+    // release they resolve to, in seconds.
+    pub(crate) cache_control_version_redirect_ttl: u32,
+
     // Activate full page caching.
     // When disabled, we still cache static assets.
     // This only affects pages that depend on invalidations to work.
@@ -92,6 +166,19 @@ pub struct Config {
     pub cloudfront_distribution_id_web: Option<String>,
     /// same for the `static.docs.rs` distribution
     pub cloudfront_distribution_id_static: Option<String>,
+
+    // Fastly API used by the admin endpoint to purge a crate's responses by
+    // surrogate key. Purging needs both the service ID and the API token.
+    pub(crate) cdn_purge_api_base: Url,
+    pub(crate) cdn_purge_service_id: Option<String>,
+    pub(crate) cdn_purge_api_token: Option<String>,
+
+    // Bearer token for the admin endpoints. Unset, they reject every request.
+    pub(crate) admin_token: Option<String>,
+
+    // former names or common typos of crates, redirected to the crate they stand for.
+    // Set as comma-separated `old=new` pairs.
+    pub(crate) crate_name_aliases: HashMap<String, String>,
     pub(crate) build_workspace_reinitialization_interval: Duration,
 
     // Build params
@@ -139,13 +226,75 @@ impl Config {
             )?),
 
             crates_io_api_call_retries: env("DOCSRS_CRATESIO_API_CALL_RETRIES", 3)?,
+            crates_io_api_call_retry_delay: Duration::from_secs(env(
+                "DOCSRS_CRATESIO_API_CALL_RETRY_DELAY",
+                2,
+            )?),
+            crates_io_api_max_retry_delay: Duration::from_secs(env(
+                "DOCSRS_CRATESIO_API_MAX_RETRY_DELAY",
+                60,
+            )?),
+            crates_io_api_call_timeout: Duration::from_secs(env(
+                "DOCSRS_CRATESIO_API_CALL_TIMEOUT",
+                30,
+            )?),
+            crates_io_crate_data_cache_ttl: Duration::from_secs(env(
+                "DOCSRS_CRATESIO_CRATE_DATA_CACHE_TTL",
+                60,
+            )?),
+            crates_io_crate_data_cache_size: env("DOCSRS_CRATESIO_CRATE_DATA_CACHE_SIZE", 1000)?,
+            crates_io_etag_cache_size: env("DOCSRS_CRATESIO_ETAG_CACHE_SIZE", 10000)?,
+            crates_io_api_max_concurrent_requests: env(
+                "DOCSRS_CRATESIO_API_MAX_CONCURRENT_REQUESTS",
+                10,
+            )?,
+            crates_io_api_min_request_interval: Duration::from_millis(env(
+                "DOCSRS_CRATESIO_API_MIN_REQUEST_INTERVAL_MS",
+                0,
+            )?),
+            crates_io_api_circuit_breaker_threshold: env(
+                "DOCSRS_CRATESIO_API_CIRCUIT_BREAKER_THRESHOLD",
+                10,
+            )?,
+            crates_io_api_circuit_breaker_cooldown: Duration::from_secs(env(
+                "DOCSRS_CRATESIO_API_CIRCUIT_BREAKER_COOLDOWN",
+                30,
+            )?),
+            crates_io_api_pool_max_idle_per_host: maybe_env(
+                "DOCSRS_CRATESIO_API_POOL_MAX_IDLE_PER_HOST",
+            )?,
+            crates_io_api_pool_idle_timeout: maybe_env::<u64>(
+                "DOCSRS_CRATESIO_API_POOL_IDLE_TIMEOUT",
+            )?
+            .map(Duration::from_secs),
+            crates_io_api_log_sample_rate: env("DOCSRS_CRATESIO_API_LOG_SAMPLE_RATE", 100)?,
+            crates_io_api_disk_cache_path: maybe_env("DOCSRS_CRATESIO_API_DISK_CACHE_PATH")?,
+            crates_io_api_disk_cache_ttl: Duration::from_secs(env(
+                "DOCSRS_CRATESIO_API_DISK_CACHE_TTL",
+                3600,
+            )?),
+            crates_io_max_versions: env("DOCSRS_CRATESIO_MAX_VERSIONS", 20_000)?,
+            crates_io_max_response_size: env(
+                "DOCSRS_CRATESIO_MAX_RESPONSE_SIZE",
+                64 * 1024 * 1024,
+            )?,
 
             registry_index_path: env("REGISTRY_INDEX_PATH", prefix.join("crates.io-index"))?,
             registry_url: maybe_env("REGISTRY_URL")?,
             registry_api_host: env(
                 "DOCSRS_REGISTRY_API_HOST",
-                "https://crates.io".parse().unwrap(),
+                DEFAULT_API_BASE.parse().unwrap(),
+            )?,
+            registry_api_offline: env("DOCSRS_REGISTRY_API_OFFLINE", false)?,
+            registry_api_user_agent: maybe_env("DOCSRS_REGISTRY_API_USER_AGENT")?,
+            registry_api_crate_path: env(
+                "DOCSRS_REGISTRY_API_CRATE_PATH",
+                "api/v1/crates/{name}".to_string(),
             )?,
+            registry_api_proxy: maybe_env("DOCSRS_REGISTRY_API_PROXY")?,
+            registry_api_no_proxy: maybe_env("DOCSRS_REGISTRY_API_NO_PROXY")?,
+            registry_sparse_index_fallback: maybe_env("DOCSRS_REGISTRY_SPARSE_INDEX_FALLBACK")?,
+            registry_avatar_proxy: maybe_env("DOCSRS_REGISTRY_AVATAR_PROXY")?,
             prefix: prefix.clone(),
 
             database_url: require_env("DOCSRS_DATABASE_URL")?,
@@ -184,9 +333,16 @@ impl Config {
             registry_gc_interval: env("DOCSRS_REGISTRY_GC_INTERVAL", 60 * 60)?,
             render_threads: env("DOCSRS_RENDER_THREADS", num_cpus::get())?,
             request_timeout: maybe_env::<u64>("DOCSRS_REQUEST_TIMEOUT")?.map(Duration::from_secs),
+            crate_rate_limit: maybe_env("DOCSRS_CRATE_RATE_LIMIT")?,
+            crate_rate_limit_burst: env("DOCSRS_CRATE_RATE_LIMIT_BURST", 20)?,
             report_request_timeouts: env("DOCSRS_REPORT_REQUEST_TIMEOUTS", false)?,
+            max_request_body_size: env("DOCSRS_MAX_REQUEST_BODY_SIZE", 1024 * 1024)?,
 
             random_crate_search_view_size: env("DOCSRS_RANDOM_CRATE_SEARCH_VIEW_SIZE", 500)?,
+            search_popularity_half_life: maybe_env::<u64>(
+                "DOCSRS_SEARCH_POPULARITY_HALF_LIFE_DAYS",
+            )?
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
 
             csp_report_only: env("DOCSRS_CSP_REPORT_ONLY", false)?,
 
@@ -194,6 +350,11 @@ impl Config {
                 "CACHE_CONTROL_STALE_WHILE_REVALIDATE",
             )?,
 
+            cache_control_version_redirect_ttl: env(
+                "DOCSRS_CACHE_CONTROL_VERSION_REDIRECT_TTL",
+                300,
+            )?,
+
             cache_invalidatable_responses: env("DOCSRS_CACHE_INVALIDATEABLE_RESPONSES", true)?,
 
             cdn_backend: env("DOCSRS_CDN_BACKEND", CdnKind::Dummy)?,
@@ -201,6 +362,20 @@ impl Config {
             cloudfront_distribution_id_web: maybe_env("CLOUDFRONT_DISTRIBUTION_ID_WEB")?,
             cloudfront_distribution_id_static: maybe_env("CLOUDFRONT_DISTRIBUTION_ID_STATIC")?,
 
+            cdn_purge_api_base: env(
+                "DOCSRS_CDN_PURGE_API_BASE",
+                "https://api.fastly.com/".parse().unwrap(),
+            )?,
+            cdn_purge_service_id: maybe_env("DOCSRS_CDN_PURGE_SERVICE_ID")?,
+            cdn_purge_api_token: maybe_env("DOCSRS_CDN_PURGE_API_TOKEN")?,
+
+            admin_token: maybe_env("DOCSRS_ADMIN_TOKEN")?,
+
+            crate_name_aliases: maybe_env::<String>("DOCSRS_CRATE_NAME_ALIASES")?
+                .map(|value| parse_crate_name_aliases(&value))
+                .transpose()?
+                .unwrap_or_default(),
+
             local_archive_cache_path: env(
                 "DOCSRS_ARCHIVE_INDEX_CACHE_PATH",
                 prefix.join("archive_cache"),
@@ -224,6 +399,21 @@ impl Config {
     }
 }
 
+/// Parse comma-separated `old=new` pairs of crate names.
+fn parse_crate_name_aliases(value: &str) -> Result<HashMap<String, String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (alias, name) = pair.split_once('=').with_context(|| {
+                format!("invalid crate name alias {pair:?} in DOCSRS_CRATE_NAME_ALIASES, expected `old=new`")
+            })?;
+            Ok((alias.trim().to_owned(), name.trim().to_owned()))
+        })
+        .collect()
+}
+
 fn env<T>(var: &str, default: T) -> Result<T>
 where
     T: FromStr,
@@ -2,7 +2,7 @@ use crate::{
     db::types::{BuildStatus, Feature},
     docbuilder::DocCoverage,
     error::Result,
-    registry_api::{CrateData, CrateOwner, ReleaseData},
+    registry_api::{CrateData, CrateOwner, RegistryApi, ReleaseData},
     storage::CompressionAlgorithm,
     utils::MetadataPackage,
     web::crate_details::{latest_release, releases_for_crate},
@@ -25,6 +25,9 @@ use tracing::{debug, info, instrument};
 ///
 /// NOTE: `source_files` refers to the files originally in the crate,
 /// not the files generated by rustdoc.
+///
+/// `registry_data` is `None` when the registry API couldn't be reached. New releases
+/// then get no release time, and existing releases keep their previous values.
 #[allow(clippy::too_many_arguments)]
 #[instrument(skip(conn))]
 pub(crate) async fn add_package_into_database(
@@ -34,7 +37,7 @@ pub(crate) async fn add_package_into_database(
     default_target: &str,
     source_files: Value,
     doc_targets: Vec<String>,
-    registry_data: &ReleaseData,
+    registry_data: Option<&ReleaseData>,
     has_docs: bool,
     has_examples: bool,
     compression_algorithms: std::collections::HashSet<CompressionAlgorithm>,
@@ -66,10 +69,10 @@ pub(crate) async fn add_package_into_database(
             $19, $20, $21, $22, $23, $24, $25
          )
          ON CONFLICT (crate_id, version) DO UPDATE
-            SET release_time = $3,
+            SET release_time = COALESCE($3, releases.release_time),
                 dependencies = $4,
                 target_name = $5,
-                yanked = $6,
+                yanked = COALESCE($6, releases.yanked),
                 rustdoc_status = $7,
                 test_status = $8,
                 license = $9,
@@ -80,7 +83,7 @@ pub(crate) async fn add_package_into_database(
                 readme = $14,
                 keywords = $15,
                 have_examples = $16,
-                downloads = $17,
+                downloads = COALESCE($17, releases.downloads),
                 files = $18,
                 doc_targets = $19,
                 is_library = $20,
@@ -92,10 +95,10 @@ pub(crate) async fn add_package_into_database(
          RETURNING id",
         crate_id,
         &metadata_pkg.version,
-        registry_data.release_time,
+        registry_data.and_then(|data| data.release_time),
         serde_json::to_value(dependencies)?,
         metadata_pkg.package_name(),
-        registry_data.yanked,
+        registry_data.map(|data| data.yanked),
         has_docs,
         false, // TODO: Add test status somehow
         metadata_pkg.license,
@@ -106,7 +109,7 @@ pub(crate) async fn add_package_into_database(
         readme,
         serde_json::to_value(&metadata_pkg.keywords)?,
         has_examples,
-        registry_data.downloads,
+        registry_data.and_then(|data| data.downloads),
         source_files,
         serde_json::to_value(doc_targets)?,
         is_library,
@@ -521,6 +524,46 @@ pub async fn update_crate_data_in_database(
     Ok(())
 }
 
+/// Updates the release time, yanked state and downloads of all releases of a crate,
+/// with a single request to the registry's API for all of them.
+///
+/// Releases the registry doesn't know are left alone. Returns how many releases
+/// were updated.
+#[instrument(skip(conn, registry_api))]
+pub async fn update_release_data_in_database(
+    conn: &mut sqlx::PgConnection,
+    registry_api: &RegistryApi,
+    name: &str,
+) -> Result<u64> {
+    info!("Updating release data for {}", name);
+    let release_data = registry_api.get_all_release_data(name).await?;
+
+    let mut updated = 0;
+    for (version, data) in release_data {
+        updated += sqlx::query(
+            "UPDATE releases
+             SET release_time = COALESCE($3, releases.release_time),
+                 yanked = $4,
+                 downloads = COALESCE($5, releases.downloads)
+             FROM crates
+             WHERE
+                releases.crate_id = crates.id AND
+                crates.name = $1 AND
+                releases.version = $2",
+        )
+        .bind(name)
+        .bind(version.to_string())
+        .bind(data.release_time)
+        .bind(data.yanked)
+        .bind(data.downloads)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(updated)
+}
+
 /// Adds owners into database
 async fn update_owners_in_database(
     conn: &mut sqlx::PgConnection,
@@ -843,6 +886,8 @@ mod test {
                 avatar: "avatar".into(),
                 login: "login".into(),
                 kind: OwnerKind::User,
+                name: None,
+                email: None,
             };
 
             update_owners_in_database(&mut conn, &[owner1.clone()], crate_id).await?;
@@ -873,6 +918,64 @@ mod test {
         })
     }
 
+    #[test]
+    fn update_release_data_of_all_versions() {
+        async_wrapper(|env| async move {
+            let mut crates_io = mockito::Server::new_async().await;
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            let mut conn = env.async_db().await.async_conn().await;
+            let crate_id = initialize_crate(&mut conn, "krate").await?;
+            initialize_release(&mut conn, crate_id, "0.1.0").await?;
+            initialize_release(&mut conn, crate_id, "0.2.0").await?;
+
+            let m = crates_io
+                .mock("GET", "/api/v1/crates/krate/versions")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    serde_json::json!({
+                        "versions": [
+                            { "num": "0.3.0", "created_at": "2024-03-01T00:00:00Z" },
+                            { "num": "0.2.0", "created_at": "2024-02-01T00:00:00Z", "downloads": 5 },
+                            { "num": "0.1.0", "created_at": "2024-01-01T00:00:00Z", "yanked": true },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let updated =
+                update_release_data_in_database(&mut conn, &env.registry_api(), "krate").await?;
+            m.assert_async().await;
+            // 0.3.0 isn't in our database
+            assert_eq!(updated, 2);
+
+            let rows: Vec<(String, Option<bool>, Option<i32>)> = sqlx::query_as(
+                "SELECT version, yanked, downloads
+                 FROM releases
+                 WHERE crate_id = $1
+                 ORDER BY version",
+            )
+            .bind(crate_id)
+            .fetch_all(&mut *conn)
+            .await?;
+            assert_eq!(
+                rows,
+                vec![
+                    ("0.1.0".into(), Some(true), Some(0)),
+                    ("0.2.0".into(), Some(false), Some(5)),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn update_owner_detais() {
         async_wrapper(|env| async move {
@@ -886,6 +989,8 @@ mod test {
                     login: "login".into(),
                     avatar: "avatar".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 }],
                 crate_id,
             )
@@ -895,6 +1000,8 @@ mod test {
                 login: "login".into(),
                 avatar: "avatar2".into(),
                 kind: OwnerKind::Team,
+                name: None,
+                email: None,
             };
             update_owners_in_database(&mut conn, &[updated_owner.clone()], crate_id).await?;
 
@@ -935,6 +1042,8 @@ mod test {
                     login: "login".into(),
                     avatar: "avatar".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 }],
                 crate_id,
             )
@@ -945,6 +1054,8 @@ mod test {
                     login: format!("login{i}"),
                     avatar: format!("avatar{i}"),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .collect();
 
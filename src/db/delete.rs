@@ -329,6 +329,8 @@ mod tests {
                     login: "malicious actor".into(),
                     avatar: "https://example.org/malicious".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
             assert!(release_exists(&mut db.conn(), v1)?);
@@ -360,6 +362,8 @@ mod tests {
                     login: "Peter Rabbit".into(),
                     avatar: "https://example.org/peter".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
             assert!(release_exists(&mut db.conn(), v2)?);
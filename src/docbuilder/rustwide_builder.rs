@@ -603,8 +603,7 @@ impl RustwideBuilder {
                         }
                     } else {
                         None
-                    }
-                    .unwrap_or_default();
+                    };
 
                     let cargo_metadata = res.cargo_metadata.root();
                     let repository = self.get_repo(cargo_metadata)?;
@@ -618,7 +617,7 @@ impl RustwideBuilder {
                         &res.target,
                         files_list,
                         successful_targets,
-                        &release_data,
+                        release_data.as_ref(),
                         has_docs,
                         has_examples,
                         algs,
@@ -114,6 +114,11 @@ metrics! {
 
         /// the number of "I'm feeling lucky" searches for crates
         pub(crate) im_feeling_lucky_searches: IntCounter,
+
+        /// Requests to the registry API, by endpoint and outcome (success, error, retry)
+        pub(crate) registry_api_requests: IntCounterVec["endpoint", "outcome"],
+        /// The response times of the registry API, by endpoint
+        pub(crate) registry_api_response_time: HistogramVec["endpoint"],
     }
 
     // The Rust prometheus library treats the namespace as the "prefix" of the metric name: a
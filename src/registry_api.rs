@@ -1,10 +1,34 @@
-use crate::{error::Result, utils::retry_async};
+use crate::{
+    error::Result,
+    metrics::{duration_to_seconds, InstanceMetrics},
+    Config,
+};
 use anyhow::{anyhow, Context};
 use chrono::{DateTime, Utc};
-use reqwest::header::{HeaderValue, ACCEPT, USER_AGENT};
+use futures_util::{stream, StreamExt as _};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::Rng as _;
+use reqwest::{
+    header::{HeaderValue, ACCEPT, ETAG, IF_NONE_MATCH, RETRY_AFTER, USER_AGENT},
+    StatusCode,
+};
 use semver::Version;
-use serde::{Deserialize, Serialize};
-use tracing::instrument;
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    io,
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+use tracing::{field, info, info_span, instrument, warn, Instrument as _};
 use url::Url;
 
 const APP_USER_AGENT: &str = concat!(
@@ -13,40 +37,392 @@ const APP_USER_AGENT: &str = concat!(
     include_str!(concat!(env!("OUT_DIR"), "/git_version"))
 );
 
+/// We stop following the reverse dependency pages after this many, at 100
+/// dependencies per page.
+const MAX_REVERSE_DEPENDENCY_PAGES: usize = 100;
+
+/// When we stored a response, with its `ETag` and body.
+type EtagCacheEntry = (Instant, HeaderValue, Vec<u8>);
+
+/// The registry API we talk to when `DOCSRS_REGISTRY_API_HOST` isn't set.
+pub(crate) const DEFAULT_API_BASE: &str = "https://crates.io";
+
+/// Shown for owners without a (usable) avatar, served from our static files.
+pub(crate) const DEFAULT_AVATAR: &str = "/-/static/default-avatar.svg";
+
 #[derive(Debug)]
 pub struct RegistryApi {
     api_base: Url,
+    /// When set, no requests are sent to `api_base`.
+    offline: bool,
     max_retries: u32,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
     client: reqwest::Client,
+    crate_data_cache: CrateDataCache,
+    /// Last `ETag` and response body per URL, used to send conditional requests.
+    etag_cache: Mutex<HashMap<Url, EtagCacheEntry>>,
+    etag_cache_size: usize,
+    layout: RegistryApiLayout,
+    /// Limits how many requests are in flight at once, over all callers.
+    request_limiter: Semaphore,
+    /// The number of permits of `request_limiter`.
+    max_concurrent_requests: usize,
+    min_request_interval: Duration,
+    /// When the next request may be started, to respect `min_request_interval`.
+    next_request_at: tokio::sync::Mutex<tokio::time::Instant>,
+    /// Optional on-disk cache of responses, for development & CI.
+    disk_cache: Option<DiskCache>,
+    /// Where we count our requests, when the metrics are set up.
+    metrics: Option<Arc<InstanceMetrics>>,
+    /// Sparse index to read release data from when the API fails.
+    sparse_index_fallback: Option<Url>,
+    /// Prefix that owner avatars are loaded through.
+    avatar_proxy: Option<String>,
+    /// Version lists with more versions are rejected.
+    max_versions: usize,
+    /// Responses with larger bodies are rejected before they are parsed.
+    max_response_size: usize,
+    circuit_breaker: CircuitBreaker,
+    log_sampler: LogSampler,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CrateData {
     pub(crate) owners: Vec<CrateOwner>,
+    pub(crate) description: Option<String>,
+    pub(crate) keywords: Vec<Keyword>,
+    pub(crate) categories: Vec<Category>,
+    pub(crate) repository: Option<String>,
+    pub(crate) homepage: Option<String>,
+    pub(crate) documentation: Option<String>,
+    pub(crate) total_downloads: i64,
+    /// downloads in the last 90 days, when the registry counts them.
+    pub(crate) recent_downloads: Option<i64>,
+    /// the newest version, prereleases included.
+    pub(crate) max_version: Option<Version>,
+    /// the newest version that isn't a prerelease, `None` when there are only prereleases.
+    pub(crate) max_stable_version: Option<Version>,
+    /// how many versions the crate has, when the registry tells us.
+    pub(crate) num_versions: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub(crate) struct ReleaseData {
-    pub(crate) release_time: DateTime<Utc>,
+    /// `None` when the registry doesn't tell us when the version was published.
+    #[serde(rename = "created_at")]
+    pub(crate) release_time: Option<DateTime<Utc>>,
     pub(crate) yanked: bool,
-    pub(crate) downloads: i32,
+    /// why the version was yanked, if the registry tells us.
+    pub(crate) yank_reason: Option<String>,
+    /// `None` when the data comes from the sparse index, which doesn't count downloads.
+    pub(crate) downloads: Option<i32>,
 }
 
-impl Default for ReleaseData {
-    fn default() -> ReleaseData {
-        ReleaseData {
-            release_time: Utc::now(),
-            yanked: false,
-            downloads: 0,
-        }
-    }
+/// How many crates `RegistryApi::warm_owner_cache` could fetch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheWarmingReport {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// The registry refuses to serve a crate, with `403 Forbidden` or
+/// `451 Unavailable For Legal Reasons`, for example after a takedown.
+#[derive(Debug, thiserror::Error)]
+#[error("the registry refuses to serve {name} ({status})")]
+pub struct CrateBlocked {
+    pub(crate) name: String,
+    pub(crate) status: StatusCode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReverseDependency {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Keyword {
+    pub(crate) name: String,
+    pub(crate) slug: String,
+    pub(crate) description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Category {
+    pub(crate) name: String,
+    pub(crate) slug: String,
+    pub(crate) description: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CrateOwner {
+    /// never empty, owners without avatar get `DEFAULT_AVATAR`.
     pub(crate) avatar: String,
     pub(crate) login: String,
     pub(crate) kind: OwnerKind,
+    /// display name, when the owner set one.
+    pub(crate) name: Option<String>,
+    /// only sent by some registries.
+    pub(crate) email: Option<String>,
+}
+
+/// Stops us from calling the registry API for a cooldown period after too many
+/// requests failed in a row, so we don't pile onto a registry that is struggling.
+///
+/// After the cooldown, a single request is let through as a probe, while the
+/// breaker stays open for everyone else. When the probe succeeds the breaker
+/// closes, when it fails the breaker opens again right away. A probe that never
+/// reports back is replaced by a new one after another cooldown.
+#[derive(Debug)]
+struct CircuitBreaker {
+    /// 0 disables the breaker.
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitBreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitBreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(CircuitBreakerState::default()),
+        }
+    }
+
+    /// Whether a request may be sent now. When the cooldown is over, the first
+    /// caller gets to send the probe.
+    fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(open_until) if Instant::now() < open_until => false,
+            Some(_) => {
+                state.open_until = Some(Instant::now() + self.cooldown);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = CircuitBreakerState::default();
+    }
+
+    fn record_failure(&self) {
+        if self.threshold == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.threshold {
+            if state
+                .open_until
+                .is_none_or(|open_until| open_until <= Instant::now())
+            {
+                warn!(
+                    failures = state.consecutive_failures,
+                    cooldown = ?self.cooldown,
+                    "too many failed registry API requests, pausing requests"
+                );
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+/// Decides which successful requests we log, to get a feel for the traffic and
+/// latency per endpoint without a log line for every single request.
+///
+/// Every `rate`-th success per endpoint is logged, starting with the first one.
+/// Failures are always logged.
+#[derive(Debug)]
+struct LogSampler {
+    /// 0 disables logging successful requests.
+    rate: u32,
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl LogSampler {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn sample(&self, endpoint: &str) -> bool {
+        if self.rate == 0 {
+            return false;
+        }
+
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(endpoint.to_owned()).or_default();
+        let sampled = count.is_multiple_of(u64::from(self.rate));
+        *count += 1;
+        sampled
+    }
+}
+
+/// How many idle connections to the registry we keep around, and for how long.
+/// Unset values keep the defaults of `reqwest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ConnectionPoolLimits {
+    max_idle_per_host: Option<usize>,
+    idle_timeout: Option<Duration>,
+}
+
+impl ConnectionPoolLimits {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            max_idle_per_host: config.crates_io_api_pool_max_idle_per_host,
+            idle_timeout: config.crates_io_api_pool_idle_timeout,
+        }
+    }
+
+    fn apply(self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(max_idle_per_host) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle_per_host);
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        builder
+    }
+}
+
+/// Small in-memory cache for crate data, so builds of several releases of the same
+/// crate in a short time don't query crates.io for the same owners again.
+#[derive(Debug)]
+struct CrateDataCache {
+    ttl: Duration,
+    max_size: usize,
+    entries: Mutex<HashMap<String, (Instant, CrateData)>>,
+}
+
+impl CrateDataCache {
+    fn new(ttl: Duration, max_size: usize) -> Self {
+        Self {
+            ttl,
+            max_size,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<CrateData> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(name) {
+            Some((inserted, data)) if inserted.elapsed() < self.ttl => Some(data.clone()),
+            Some(_) => {
+                entries.remove(name);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, name: &str, data: CrateData) {
+        if self.max_size == 0 || self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_size && !entries.contains_key(name) {
+            entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+        }
+        if entries.len() >= self.max_size && !entries.contains_key(name) {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (inserted, _))| *inserted)
+                .map(|(name, _)| name.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(name.to_owned(), (Instant::now(), data));
+    }
+
+    #[cfg(test)]
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Registry API responses cached on disk, one file per URL.
+///
+/// Failing to read or write the cache is only logged, we'll just ask the registry then.
+#[derive(Debug)]
+struct DiskCache {
+    path: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiskCacheEntry {
+    url: Url,
+    fetched_at: DateTime<Utc>,
+    body: serde_json::Value,
+}
+
+impl DiskCache {
+    fn new(path: PathBuf, ttl: Duration) -> Self {
+        Self { path, ttl }
+    }
+
+    fn entry_path(&self, url: &Url) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        self.path.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    async fn get(&self, url: &Url) -> Option<serde_json::Value> {
+        let content = match tokio::fs::read(self.entry_path(url)).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!(%url, ?err, "could not read registry API disk cache");
+                return None;
+            }
+        };
+
+        let entry: DiskCacheEntry = match serde_json::from_slice(&content) {
+            Ok(entry) => entry,
+            Err(err) => {
+                warn!(%url, ?err, "invalid registry API disk cache entry");
+                return None;
+            }
+        };
+
+        // the file name is only a hash, so check we got the right URL
+        let age = (Utc::now() - entry.fetched_at).to_std().unwrap_or_default();
+        (entry.url == *url && age < self.ttl).then_some(entry.body)
+    }
+
+    async fn insert(&self, url: &Url, body: &[u8]) {
+        let result = async {
+            let entry = DiskCacheEntry {
+                url: url.clone(),
+                fetched_at: Utc::now(),
+                body: serde_json::from_slice(body)?,
+            };
+            tokio::fs::create_dir_all(&self.path).await?;
+            tokio::fs::write(self.entry_path(url), serde_json::to_vec(&entry)?).await?;
+            anyhow::Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            warn!(%url, ?err, "could not write registry API disk cache");
+        }
+    }
 }
 
 #[derive(
@@ -60,117 +436,723 @@ pub enum OwnerKind {
 }
 
 impl RegistryApi {
-    pub fn new(api_base: Url, max_retries: u32) -> Result<Self> {
+    pub fn new(config: &Config) -> Result<Self> {
+        let user_agent = match config.registry_api_user_agent {
+            Some(ref user_agent) => HeaderValue::from_str(user_agent)
+                .with_context(|| format!("invalid registry API user agent: {user_agent:?}"))?,
+            None => HeaderValue::from_static(APP_USER_AGENT),
+        };
+
         let headers = vec![
-            (USER_AGENT, HeaderValue::from_static(APP_USER_AGENT)),
+            (USER_AGENT, user_agent),
             (ACCEPT, HeaderValue::from_static("application/json")),
         ]
         .into_iter()
         .collect();
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?;
+        let mut builder = ConnectionPoolLimits::from_config(config).apply(
+            reqwest::Client::builder()
+                .default_headers(headers)
+                // large responses like the versions list are a lot smaller compressed
+                .gzip(true)
+                .brotli(true)
+                .deflate(true)
+                .timeout(config.crates_io_api_call_timeout),
+        );
+
+        if let Some(ref proxy_url) = config.registry_api_proxy {
+            let proxy = reqwest::Proxy::all(proxy_url.clone())
+                .with_context(|| format!("invalid registry API proxy: {proxy_url}"))?
+                .no_proxy(
+                    config
+                        .registry_api_no_proxy
+                        .as_deref()
+                        .and_then(reqwest::NoProxy::from_string),
+                );
+            builder = builder.proxy(proxy);
+        }
+
+        let client = builder.build()?;
+
+        if config.registry_api_offline {
+            info!("the registry API is disabled, running offline");
+        } else {
+            info!(api_base = %config.registry_api_host, "using the registry API");
+        }
 
         Ok(Self {
-            api_base,
+            api_base: config.registry_api_host.clone(),
+            offline: config.registry_api_offline,
             client,
-            max_retries,
+            max_retries: config.crates_io_api_call_retries,
+            retry_delay: config.crates_io_api_call_retry_delay,
+            max_retry_delay: config.crates_io_api_max_retry_delay,
+            crate_data_cache: CrateDataCache::new(
+                config.crates_io_crate_data_cache_ttl,
+                config.crates_io_crate_data_cache_size,
+            ),
+            etag_cache: Mutex::new(HashMap::new()),
+            etag_cache_size: config.crates_io_etag_cache_size,
+            layout: RegistryApiLayout::from_template(&config.registry_api_crate_path)?,
+            request_limiter: Semaphore::new(config.crates_io_api_max_concurrent_requests),
+            max_concurrent_requests: config.crates_io_api_max_concurrent_requests,
+            min_request_interval: config.crates_io_api_min_request_interval,
+            next_request_at: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+            disk_cache: config
+                .crates_io_api_disk_cache_path
+                .as_ref()
+                .map(|path| DiskCache::new(path.clone(), config.crates_io_api_disk_cache_ttl)),
+            metrics: None,
+            sparse_index_fallback: config.registry_sparse_index_fallback.clone(),
+            avatar_proxy: config.registry_avatar_proxy.clone(),
+            max_versions: config.crates_io_max_versions,
+            max_response_size: config.crates_io_max_response_size,
+            circuit_breaker: CircuitBreaker::new(
+                config.crates_io_api_circuit_breaker_threshold,
+                config.crates_io_api_circuit_breaker_cooldown,
+            ),
+            log_sampler: LogSampler::new(config.crates_io_api_log_sample_rate),
         })
     }
 
+    /// Record the outcome and response time of our requests in the given metrics.
+    pub fn with_metrics(mut self, metrics: Arc<InstanceMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    fn record_request(&self, endpoint: &str, outcome: &str, elapsed: Duration) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .registry_api_requests
+                .with_label_values(&[endpoint, outcome])
+                .inc();
+            metrics
+                .registry_api_response_time
+                .with_label_values(&[endpoint])
+                .observe(duration_to_seconds(elapsed));
+        }
+    }
+
+    /// Check that the registry API is reachable, for readiness probes.
+    ///
+    /// This is a single `HEAD` request to the API base, without retries.
+    pub async fn ping(&self) -> Result<()> {
+        const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+        if self.offline {
+            return Err(offline_error());
+        }
+
+        self.client
+            .head(self.api_base.clone())
+            .timeout(PING_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Forget all cached crate data.
+    #[cfg(test)]
+    pub(crate) fn clear_crate_data_cache(&self) {
+        self.crate_data_cache.clear();
+    }
+
+    /// GET the given URL, retrying with exponential backoff when the request fails
+    /// or the registry responds with `429 Too Many Requests` or a server error.
+    /// A `Retry-After` header sent by the registry takes precedence over our own delay,
+    /// which is randomized a bit so that many clients don't retry at the same time.
+    /// Either way we never wait longer than `DOCSRS_CRATESIO_API_MAX_RETRY_DELAY`.
+    ///
+    /// While the circuit breaker is open, requests to the registry API fail without
+    /// being sent.
+    ///
+    /// Every attempt is counted in the metrics under the given `endpoint`. Failed
+    /// attempts are always logged, successful ones only sampled.
+    async fn get_with_retries(
+        &self,
+        endpoint: &str,
+        url: &Url,
+        etag: Option<&HeaderValue>,
+    ) -> Result<reqwest::Response> {
+        // the breaker only protects the registry API, not for example the sparse index
+        let use_circuit_breaker = url.origin() == self.api_base.origin();
+        if use_circuit_breaker && self.offline {
+            return Err(offline_error());
+        }
+
+        let mut attempt = 0;
+        loop {
+            if use_circuit_breaker && !self.circuit_breaker.allow_request() {
+                return Err(anyhow!(
+                    "registry API requests are paused after too many failures"
+                ));
+            }
+
+            let mut request = self.client.get(url.clone());
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag.clone());
+            }
+
+            let span = info_span!(
+                "registry_api_request",
+                %url,
+                attempt,
+                status = field::Empty,
+                elapsed_ms = field::Empty,
+            );
+            let start = Instant::now();
+            let response = self.send_limited(request).instrument(span.clone()).await;
+            let elapsed = start.elapsed();
+
+            span.record("elapsed_ms", elapsed.as_millis() as u64);
+            span.in_scope(|| match &response {
+                Ok(response) => {
+                    let status = response.status();
+                    span.record("status", status.as_u16());
+                    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+                        if self.log_sampler.sample(endpoint) {
+                            info!(
+                                endpoint,
+                                status = status.as_u16(),
+                                ?elapsed,
+                                sample_rate = self.log_sampler.rate,
+                                "registry API request succeeded"
+                            );
+                        }
+                    } else {
+                        warn!(
+                            status = status.as_u16(),
+                            ?elapsed,
+                            "registry API request failed"
+                        );
+                    }
+                }
+                Err(err) => warn!(?err, ?elapsed, "registry API request failed"),
+            });
+
+            let response = match response {
+                Ok(response) if !is_retryable(response.status()) => {
+                    if use_circuit_breaker {
+                        self.circuit_breaker.record_success();
+                    }
+                    let status = response.status();
+                    let outcome = if status.is_client_error() || status.is_server_error() {
+                        "error"
+                    } else {
+                        "success"
+                    };
+                    self.record_request(endpoint, outcome, elapsed);
+                    return error_for_status(response).await;
+                }
+                other => {
+                    if use_circuit_breaker {
+                        self.circuit_breaker.record_failure();
+                    }
+                    other
+                }
+            };
+
+            let retry_after = response
+                .as_ref()
+                .ok()
+                .and_then(|response| response.headers().get(RETRY_AFTER))
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
+
+            if attempt >= self.max_retries {
+                self.record_request(endpoint, "error", elapsed);
+                return error_for_status(response?).await;
+            }
+            attempt += 1;
+            self.record_request(endpoint, "retry", elapsed);
+
+            let sleep_for = retry_after
+                .unwrap_or_else(|| {
+                    self.retry_delay
+                        .saturating_mul(2u32.checked_pow(attempt - 1).unwrap_or(u32::MAX))
+                        .min(self.max_retry_delay)
+                        .mul_f64(rand::thread_rng().gen_range(0.5..1.5))
+                })
+                .min(self.max_retry_delay);
+            warn!(
+                %url,
+                attempt,
+                ?sleep_for,
+                "request to registry API failed, will try again"
+            );
+            tokio::time::sleep(sleep_for).await;
+        }
+    }
+
+    /// Send the request once our rate limits allow it.
+    async fn send_limited(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let _permit = self
+            .request_limiter
+            .acquire()
+            .await
+            .expect("we never close the semaphore");
+
+        if !self.min_request_interval.is_zero() {
+            let mut next_request_at = self.next_request_at.lock().await;
+            tokio::time::sleep_until(*next_request_at).await;
+            *next_request_at = tokio::time::Instant::now() + self.min_request_interval;
+        }
+
+        request.send().await
+    }
+
+    /// GET the given URL and parse the JSON response.
+    ///
+    /// When we have seen an `ETag` for this URL before, the request is made conditional,
+    /// and on `304 Not Modified` the previously received body is reused.
+    /// When the disk cache is enabled and has a fresh response, the registry isn't
+    /// asked at all.
+    async fn get_json<T: DeserializeOwned>(&self, endpoint: &str, url: &Url) -> Result<T> {
+        self.get_json_seed(endpoint, url, PhantomData).await
+    }
+
+    /// Like `get_json`, but parse the response with `seed`, for example to
+    /// limit what we read into memory.
+    async fn get_json_seed<S, T>(&self, endpoint: &str, url: &Url, seed: S) -> Result<T>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = T>,
+    {
+        if let Some(disk_cache) = &self.disk_cache {
+            if let Some(value) = disk_cache.get(url).await {
+                return Ok(seed.deserialize(value)?);
+            }
+        }
+
+        let cached = self
+            .etag_cache
+            .lock()
+            .unwrap()
+            .get(url)
+            .map(|(_, etag, body)| (etag.clone(), body.clone()));
+
+        let response = self
+            .get_with_retries(endpoint, url, cached.as_ref().map(|(etag, _)| etag))
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((_, body)) = cached {
+                return parse_json_seed(&body, seed);
+            }
+        }
+
+        let etag = response.headers().get(ETAG).cloned();
+        let body = read_limited_body(response, self.max_response_size).await?;
+        let value = parse_json_seed(&body, seed)?;
+
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.insert(url, &body).await;
+        }
+
+        if let Some(etag) = etag {
+            let mut etag_cache = self.etag_cache.lock().unwrap();
+            if etag_cache.len() >= self.etag_cache_size && !etag_cache.contains_key(url) {
+                if let Some(oldest) = etag_cache
+                    .iter()
+                    .min_by_key(|(_, (inserted, _, _))| *inserted)
+                    .map(|(url, _)| url.clone())
+                {
+                    etag_cache.remove(&oldest);
+                }
+            }
+            if self.etag_cache_size > 0 {
+                etag_cache.insert(url.clone(), (Instant::now(), etag, body));
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Get the owners and the metadata of a crate.
+    ///
+    /// When only the metadata can't be fetched, it's logged and left empty, so
+    /// we still know the owners. Such partial data isn't cached.
     #[instrument(skip(self))]
     pub async fn get_crate_data(&self, name: &str) -> Result<CrateData> {
+        if let Some(data) = self.crate_data_cache.get(name) {
+            return Ok(data);
+        }
+
         let owners = self
             .get_owners(name)
             .await
             .context(format!("Failed to get owners for {name}"))?;
+        let (metadata, keywords, categories) = match self.get_crate_metadata(name).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.is::<CrateBlocked>() => {
+                return Err(err.context(format!("Failed to get metadata for {name}")));
+            }
+            Err(err) => {
+                warn!(
+                    name,
+                    ?err,
+                    "could not get crate metadata, only using the owners"
+                );
+                return Ok(CrateData {
+                    owners,
+                    ..Default::default()
+                });
+            }
+        };
+
+        let (total_downloads, recent_downloads) = metadata.download_counts(name);
+
+        let data = CrateData {
+            owners,
+            description: metadata.description,
+            keywords,
+            categories,
+            repository: metadata.repository,
+            homepage: metadata.homepage,
+            documentation: metadata.documentation,
+            total_downloads,
+            recent_downloads,
+            max_version: parse_version_field(name, "max_version", metadata.max_version.as_deref()),
+            max_stable_version: parse_version_field(
+                name,
+                "max_stable_version",
+                metadata.max_stable_version.as_deref(),
+            ),
+            num_versions: metadata.versions.as_ref().map(Vec::len),
+        };
+        self.crate_data_cache.insert(name, data.clone());
+
+        Ok(data)
+    }
+
+    /// Fetch the crate data for many crates concurrently, as far as our
+    /// request limits allow.
+    ///
+    /// Only as many crates are fetched at once as we allow concurrent requests,
+    /// so long lists don't queue up a future per crate. The result is in no
+    /// particular order, crates we can't fetch are logged and left out of it.
+    #[instrument(skip_all, fields(crates = names.len()))]
+    pub async fn get_crates_data(&self, names: &[&str]) -> Vec<(String, CrateData)> {
+        stream::iter(names)
+            .map(|&name| async move { (name, self.get_crate_data(name).await) })
+            .buffer_unordered(self.max_concurrent_requests.max(1))
+            .filter_map(|(name, result)| async move {
+                match result {
+                    Ok(data) => Some((name.to_owned(), data)),
+                    Err(err) => {
+                        warn!(name, ?err, "could not get crate data");
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await
+    }
+
+    /// Fetch the owners and other crate data of many crates ahead of time, so a
+    /// following reindex finds them in the in-process cache instead of calling the
+    /// registry API in the middle of the run.
+    ///
+    /// The requests run concurrently, as far as our request limits allow. Only as
+    /// many crates as the cache holds stay cached, and only for the cache TTL.
+    #[instrument(skip_all, fields(crates = names.len()))]
+    pub async fn warm_owner_cache(&self, names: &[&str]) -> CacheWarmingReport {
+        let fetched = self.get_crates_data(names).await.len();
+        let report = CacheWarmingReport {
+            succeeded: fetched,
+            failed: names.len() - fetched,
+        };
 
-        Ok(CrateData { owners })
+        info!(
+            succeeded = report.succeeded,
+            failed = report.failed,
+            "warmed the owner cache"
+        );
+        report
     }
 
+    /// Get the release data of a version.
+    ///
+    /// When the API fails and a sparse index fallback is configured, the yanked state
+    /// is read from the index instead. The index doesn't know the release time or
+    /// the downloads, so these stay unknown.
+    ///
+    /// For several versions of the same crate, `get_all_release_data` needs only
+    /// a single request.
     #[instrument(skip(self))]
     pub(crate) async fn get_release_data(&self, name: &str, version: &str) -> Result<ReleaseData> {
-        let (release_time, yanked, downloads) = self
-            .get_release_time_yanked_downloads(name, version)
-            .await
-            .context(format!("Failed to get crate data for {name}-{version}"))?;
+        let data = match self.get_version_data(name, version).await {
+            Ok(data) => data,
+            // the index would still know the crate, but we shouldn't serve it
+            Err(err) if err.is::<CrateBlocked>() => {
+                return Err(err.context(format!("Failed to get crate data for {name}-{version}")))
+            }
+            Err(err) => {
+                let Some(index) = &self.sparse_index_fallback else {
+                    return Err(
+                        err.context(format!("Failed to get crate data for {name}-{version}"))
+                    );
+                };
+                warn!(
+                    ?err,
+                    "registry API failed, reading release data from the sparse index"
+                );
+                return self
+                    .get_release_data_from_index(index, name, version)
+                    .await
+                    .context(format!("Failed to get crate data for {name}-{version}"));
+            }
+        };
 
         Ok(ReleaseData {
-            release_time,
-            yanked,
-            downloads,
+            release_time: data.created_at,
+            yanked: data.yanked,
+            yank_reason: data.yank_reason,
+            downloads: Some(data.downloads),
         })
     }
 
-    /// Get release_time, yanked and downloads from the registry's API
-    async fn get_release_time_yanked_downloads(
+    /// Get the release data of all versions of a crate, with a single request.
+    ///
+    /// Unlike `get_release_data` this doesn't fall back to the sparse index.
+    #[instrument(skip(self))]
+    pub(crate) async fn get_all_release_data(
+        &self,
+        name: &str,
+    ) -> Result<HashMap<Version, ReleaseData>> {
+        let versions = self
+            .get_version_list(name)
+            .await
+            .map_err(|err| blocked_or(err, name))
+            .with_context(|| format!("Failed to get release data for {name}"))?;
+
+        Ok(versions
+            .into_iter()
+            .map(|data| {
+                (
+                    data.num,
+                    ReleaseData {
+                        release_time: data.created_at,
+                        yanked: data.yanked,
+                        yank_reason: data.yank_reason,
+                        downloads: Some(data.downloads),
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Read the yanked state of a version from the crate's file in a sparse index.
+    async fn get_release_data_from_index(
         &self,
+        index: &Url,
         name: &str,
         version: &str,
-    ) -> Result<(DateTime<Utc>, bool, i32)> {
-        let url = {
-            let mut url = self.api_base.clone();
-            url.path_segments_mut()
-                .map_err(|()| anyhow!("Invalid API url"))?
-                .extend(&["api", "v1", "crates", name, "versions"]);
-            url
-        };
+    ) -> Result<ReleaseData> {
+        let version = Version::parse(version)?;
+        let url = sparse_index_url(index, name)?;
+
+        #[derive(Deserialize)]
+        struct IndexEntry {
+            vers: Version,
+            #[serde(default)]
+            yanked: bool,
+        }
+
+        let body = self
+            .get_with_retries("sparse_index", &url, None)
+            .await?
+            .text()
+            .await?;
+
+        for line in body.lines().filter(|line| !line.trim().is_empty()) {
+            let entry: IndexEntry = serde_json::from_str(line)
+                .with_context(|| format!("invalid line in sparse index file for {name}"))?;
+            if entry.vers == version {
+                return Ok(ReleaseData {
+                    release_time: None,
+                    yanked: entry.yanked,
+                    yank_reason: None,
+                    downloads: None,
+                });
+            }
+        }
+
+        Err(anyhow!("Could not find version in sparse index"))
+    }
+
+    /// Get the release time, yanked state and downloads of a version from the registry's API.
+    ///
+    /// We first ask for the single version, and only fall back to fetching the list of
+    /// all versions when the registry doesn't know that endpoint.
+    #[instrument(skip(self))]
+    async fn get_version_data(&self, name: &str, version: &str) -> Result<VersionData> {
+        let version = Version::parse(version)?;
+
+        match self.get_version(name, &version).await {
+            Ok(data) => Ok(data),
+            Err(err) if is_not_found(&err) => self
+                .get_version_from_list(name, &version)
+                .await
+                .map_err(|err| blocked_or(err, name)),
+            Err(err) => Err(blocked_or(err, name)),
+        }
+    }
+
+    /// Fetch a single version from the registry's API
+    async fn get_version(&self, name: &str, version: &Version) -> Result<VersionData> {
+        let url = self
+            .layout
+            .crate_url(&self.api_base, name, &[&version.to_string()])?;
 
         #[derive(Deserialize)]
         struct Response {
-            versions: Vec<VersionData>,
+            version: VersionData,
+        }
+
+        let response: Response = self.get_json("version", &url).await?;
+
+        if response.version.num != *version {
+            return Err(anyhow!("Could not find version in response"));
         }
 
+        Ok(response.version)
+    }
+
+    /// Fetch all versions of a crate from the registry's API, at most `max_versions`.
+    async fn get_version_list(&self, name: &str) -> Result<Vec<VersionData>> {
+        let url = self.layout.crate_url(&self.api_base, name, &["versions"])?;
+
+        self.get_json_seed(
+            "versions",
+            &url,
+            VersionListSeed {
+                max_versions: self.max_versions,
+            },
+        )
+        .await
+    }
+
+    /// Fetch all versions from the registry's API and pick the one we need
+    async fn get_version_from_list(&self, name: &str, version: &Version) -> Result<VersionData> {
+        self.get_version_list(name)
+            .await?
+            .into_iter()
+            .find(|data| data.num == *version)
+            .with_context(|| anyhow!("Could not find version in response"))
+    }
+
+    /// Fetch the crate's description, keywords, categories and links from the registry's API
+    async fn get_crate_metadata(
+        &self,
+        name: &str,
+    ) -> Result<(CrateMetadata, Vec<Keyword>, Vec<Category>)> {
+        let url = self.layout.crate_url(&self.api_base, name, &[])?;
+
         #[derive(Deserialize)]
-        struct VersionData {
-            num: Version,
-            #[serde(default = "Utc::now")]
-            created_at: DateTime<Utc>,
+        struct Response {
+            #[serde(rename = "crate")]
+            krate: CrateMetadata,
+            // details of the keywords & categories, parsed separately so broken
+            // entries don't fail the whole response.
             #[serde(default)]
-            yanked: bool,
+            keywords: serde_json::Value,
             #[serde(default)]
-            downloads: i32,
+            categories: serde_json::Value,
         }
 
-        let response: Response = retry_async(
-            || async {
-                Ok(self
-                    .client
-                    .get(url.clone())
-                    .send()
-                    .await?
-                    .error_for_status()?)
-            },
-            self.max_retries,
-        )
-        .await?
-        .json()
-        .await?;
+        #[derive(Deserialize)]
+        struct KeywordData {
+            id: String,
+            #[serde(default)]
+            keyword: Option<String>,
+        }
 
-        let version = Version::parse(version)?;
-        let version = response
-            .versions
+        #[derive(Deserialize)]
+        struct CategoryData {
+            id: String,
+            #[serde(default)]
+            slug: Option<String>,
+            #[serde(default)]
+            category: Option<String>,
+            #[serde(default)]
+            description: Option<String>,
+        }
+
+        fn details<T: DeserializeOwned>(
+            name: &str,
+            kind: &str,
+            value: serde_json::Value,
+        ) -> Vec<T> {
+            if value.is_null() {
+                return Vec::new();
+            }
+            serde_json::from_value(value).unwrap_or_else(|err| {
+                warn!(name, kind, ?err, "invalid details in registry API response");
+                Vec::new()
+            })
+        }
+
+        let response: Response = self.get_json("crate", &url).await?;
+
+        let keyword_details: Vec<KeywordData> = details(name, "keywords", response.keywords);
+        let keywords = response
+            .krate
+            .keywords
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| {
+                let details = keyword_details.iter().find(|data| data.id == id);
+                Keyword {
+                    name: details
+                        .and_then(|data| data.keyword.clone())
+                        .unwrap_or_else(|| id.clone()),
+                    slug: id,
+                    description: None,
+                }
+            })
+            .collect();
+
+        let category_details: Vec<CategoryData> = details(name, "categories", response.categories);
+        let categories = response
+            .krate
+            .categories
+            .clone()
+            .unwrap_or_default()
             .into_iter()
-            .find(|data| data.num == version)
-            .with_context(|| anyhow!("Could not find version in response"))?;
+            .map(|id| {
+                let details = category_details.iter().find(|data| data.id == id);
+                Category {
+                    name: details
+                        .and_then(|data| data.category.clone())
+                        .unwrap_or_else(|| id.clone()),
+                    slug: details.and_then(|data| data.slug.clone()).unwrap_or(id),
+                    description: details.and_then(|data| data.description.clone()),
+                }
+            })
+            .collect();
 
-        Ok((version.created_at, version.yanked, version.downloads))
+        Ok((response.krate, keywords, categories))
     }
 
     /// Fetch owners from the registry's API
+    #[instrument(skip(self))]
     async fn get_owners(&self, name: &str) -> Result<Vec<CrateOwner>> {
-        let url = {
-            let mut url = self.api_base.clone();
-            url.path_segments_mut()
-                .map_err(|()| anyhow!("Invalid API url"))?
-                .extend(&["api", "v1", "crates", name, "owners"]);
-            url
-        };
+        let url = self.layout.crate_url(&self.api_base, name, &["owners"])?;
 
         #[derive(Deserialize)]
         struct Response {
+            #[serde(default)]
             users: Vec<OwnerData>,
+            #[serde(default)]
+            teams: Vec<OwnerData>,
         }
 
         #[derive(Deserialize)]
@@ -181,40 +1163,2718 @@ impl RegistryApi {
             login: Option<String>,
             #[serde(default)]
             kind: Option<OwnerKind>,
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default)]
+            email: Option<String>,
         }
 
-        let response: Response = retry_async(
-            || async {
-                Ok(self
-                    .client
-                    .get(url.clone())
-                    .send()
-                    .await?
-                    .error_for_status()?)
-            },
-            self.max_retries,
-        )
-        .await?
-        .json()
-        .await?;
+        let response: Response = self
+            .get_json("owners", &url)
+            .await
+            .map_err(|err| blocked_or(err, name))?;
 
         let result = response
             .users
             .into_iter()
-            .filter(|data| {
+            .map(|data| (data, OwnerKind::User))
+            .chain(
+                response
+                    .teams
+                    .into_iter()
+                    .map(|data| (data, OwnerKind::Team)),
+            )
+            .filter(|(data, _)| {
                 !data
                     .login
                     .as_ref()
                     .map(|login| login.is_empty())
                     .unwrap_or_default()
             })
-            .map(|data| CrateOwner {
-                avatar: data.avatar.unwrap_or_default(),
+            .map(|(data, default_kind)| CrateOwner {
+                avatar: self.avatar_url(data.avatar.as_deref()),
                 login: data.login.unwrap_or_default(),
-                kind: data.kind.unwrap_or(OwnerKind::User),
+                kind: data.kind.unwrap_or(default_kind),
+                name: data.name,
+                email: data.email,
             })
             .collect();
 
         Ok(result)
     }
+
+    /// Make an owner's avatar URL safe to embed in our pages.
+    ///
+    /// With an avatar proxy, all avatars are loaded through it. Without one, we only
+    /// keep HTTPS URLs, to avoid mixed content. Missing or invalid avatars are
+    /// replaced with our default avatar.
+    fn avatar_url(&self, avatar: Option<&str>) -> String {
+        let Some(url) = avatar
+            .filter(|avatar| !avatar.is_empty())
+            .and_then(|avatar| Url::parse(avatar).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+        else {
+            return DEFAULT_AVATAR.into();
+        };
+
+        match self.avatar_proxy {
+            Some(ref proxy) => format!(
+                "{proxy}{}",
+                utf8_percent_encode(url.as_str(), NON_ALPHANUMERIC)
+            ),
+            None if url.scheme() == "https" => url.into(),
+            None => DEFAULT_AVATAR.into(),
+        }
+    }
+
+    /// Fetch the crates depending on the given crate from the registry's API,
+    /// following the result pages.
+    ///
+    /// We follow at most `MAX_REVERSE_DEPENDENCY_PAGES` pages, and stop early when
+    /// the registry links to a page we already fetched, so a misbehaving registry
+    /// can't keep us in a loop. The dependencies up to that point are returned.
+    #[instrument(skip(self))]
+    pub async fn get_reverse_dependencies(&self, name: &str) -> Result<Vec<ReverseDependency>> {
+        let mut url = self
+            .layout
+            .crate_url(&self.api_base, name, &["reverse_dependencies"])?;
+        url.set_query(Some("page=1&per_page=100"));
+
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(default)]
+            dependencies: Vec<DependencyData>,
+            #[serde(default)]
+            versions: Vec<DependentVersionData>,
+            #[serde(default)]
+            meta: MetaData,
+        }
+
+        #[derive(Deserialize)]
+        struct DependencyData {
+            version_id: i64,
+            #[serde(default)]
+            kind: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct DependentVersionData {
+            id: i64,
+            #[serde(default, rename = "crate")]
+            krate: Option<String>,
+            #[serde(default)]
+            num: Option<String>,
+        }
+
+        #[derive(Deserialize, Default)]
+        struct MetaData {
+            #[serde(default)]
+            next_page: Option<String>,
+        }
+
+        let mut result = Vec::new();
+        let mut seen_urls = HashSet::new();
+        loop {
+            if seen_urls.len() >= MAX_REVERSE_DEPENDENCY_PAGES {
+                warn!(name, %url, "too many reverse dependency pages, stopping");
+                break;
+            }
+            if !seen_urls.insert(url.clone()) {
+                warn!(name, %url, "reverse dependency page was already fetched, stopping");
+                break;
+            }
+
+            let response: Response = self
+                .get_json("reverse_dependencies", &url)
+                .await
+                .context(format!("Failed to get reverse dependencies for {name}"))?;
+
+            result.extend(response.dependencies.into_iter().filter_map(|dependency| {
+                let version = response
+                    .versions
+                    .iter()
+                    .find(|version| version.id == dependency.version_id)?;
+                let name = version.krate.clone().filter(|name| !name.is_empty())?;
+
+                Some(ReverseDependency {
+                    name,
+                    version: version.num.clone().unwrap_or_default(),
+                    kind: dependency.kind.unwrap_or_else(|| "normal".into()),
+                })
+            }));
+
+            match response.meta.next_page {
+                Some(next_page) => url = url.join(&next_page)?,
+                None => break,
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Describes where the per-crate endpoints live on the registry's API.
+///
+/// The template is a `/`-separated path relative to the API base, in which `{name}`
+/// is replaced by the crate name. For crates.io this is `api/v1/crates/{name}`, and
+/// endpoints like `owners` or `versions` are appended to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RegistryApiLayout {
+    segments: Vec<String>,
+}
+
+impl Default for RegistryApiLayout {
+    fn default() -> Self {
+        Self::from_template("api/v1/crates/{name}").expect("valid default layout")
+    }
+}
+
+impl RegistryApiLayout {
+    pub(crate) fn from_template(template: &str) -> Result<Self> {
+        let segments: Vec<String> = template
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect();
+
+        if !segments.iter().any(|segment| segment == "{name}") {
+            return Err(anyhow!(
+                "registry API path template {template:?} doesn't contain `{{name}}`"
+            ));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Build the URL of a crate endpoint below `base`.
+    fn crate_url(&self, base: &Url, name: &str, endpoint: &[&str]) -> Result<Url> {
+        let mut url = base.clone();
+        url.path_segments_mut()
+            .map_err(|()| anyhow!("Invalid API url"))?
+            .pop_if_empty()
+            .extend(self.segments.iter().map(|segment| {
+                if segment == "{name}" {
+                    name
+                } else {
+                    segment.as_str()
+                }
+            }))
+            .extend(endpoint);
+        Ok(url)
+    }
+}
+
+#[derive(Deserialize)]
+struct CrateMetadata {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    keywords: Option<Vec<String>>,
+    #[serde(default)]
+    categories: Option<Vec<String>>,
+    #[serde(default)]
+    repository: Option<String>,
+    #[serde(default)]
+    homepage: Option<String>,
+    #[serde(default)]
+    documentation: Option<String>,
+    #[serde(default)]
+    downloads: Option<i64>,
+    #[serde(default)]
+    recent_downloads: Option<i64>,
+    // parsed separately, so a malformed version doesn't fail the whole response.
+    #[serde(default)]
+    max_version: Option<String>,
+    #[serde(default)]
+    max_stable_version: Option<String>,
+    // the IDs of the versions, we only count them.
+    #[serde(default)]
+    versions: Option<Vec<serde_json::Value>>,
+}
+
+impl CrateMetadata {
+    /// The total and recent download counts of the crate.
+    ///
+    /// Not every registry counts downloads, so a missing total is logged
+    /// and treated as zero.
+    fn download_counts(&self, name: &str) -> (i64, Option<i64>) {
+        let total = self.downloads.unwrap_or_else(|| {
+            warn!(name, "registry API sent no download count");
+            0
+        });
+
+        (total, self.recent_downloads)
+    }
+}
+
+/// Parse a version field of a registry API response, logging malformed values
+/// instead of failing the whole response.
+fn parse_version_field(name: &str, field: &str, value: Option<&str>) -> Option<Version> {
+    let value = value?;
+    match Version::parse(value) {
+        Ok(version) => Some(version),
+        Err(err) => {
+            warn!(
+                name,
+                field,
+                value,
+                ?err,
+                "invalid version in registry API response"
+            );
+            None
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VersionData {
+    num: Version,
+    // a malformed timestamp is an error, only a missing one is tolerated.
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    yanked: bool,
+    // not all registries send this
+    #[serde(default, rename = "yank_message")]
+    yank_reason: Option<String>,
+    #[serde(default)]
+    downloads: i32,
+}
+
+fn offline_error() -> anyhow::Error {
+    anyhow!("the registry API is disabled, we're running offline")
+}
+
+/// Parses the `versions` of a version list response, and fails as soon as there
+/// are more than `max_versions`, instead of reading all of them into memory.
+#[derive(Debug, Clone, Copy)]
+struct VersionListSeed {
+    max_versions: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for VersionListSeed {
+    type Value = Vec<VersionData>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for VersionListSeed {
+    type Value = Vec<VersionData>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a response with a list of versions")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut versions = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "versions" {
+                versions = Some(map.next_value_seed(LimitedVersions(self.max_versions))?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        versions.ok_or_else(|| de::Error::missing_field("versions"))
+    }
+}
+
+/// The list of versions itself, see `VersionListSeed`.
+struct LimitedVersions(usize);
+
+impl<'de> DeserializeSeed<'de> for LimitedVersions {
+    type Value = Vec<VersionData>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for LimitedVersions {
+    type Value = Vec<VersionData>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a list of at most {} versions", self.0)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut versions = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(self.0));
+        while let Some(version) = seq.next_element::<VersionData>()? {
+            if versions.len() >= self.0 {
+                return Err(de::Error::custom(format_args!(
+                    "the crate has more than {} versions",
+                    self.0
+                )));
+            }
+            versions.push(version);
+        }
+        Ok(versions)
+    }
+}
+
+/// Read the body of `response`, and fail as soon as it is larger than `limit` bytes,
+/// before reading all of it into memory.
+async fn read_limited_body(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>> {
+    let too_large = || anyhow!("the registry response is larger than {limit} bytes");
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn parse_json_seed<S, T>(body: &[u8], seed: S) -> Result<T>
+where
+    S: for<'de> DeserializeSeed<'de, Value = T>,
+{
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = seed.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .and_then(|err| err.status())
+        == Some(StatusCode::NOT_FOUND)
+}
+
+/// Like `reqwest::Response::error_for_status`, but the error includes the reasons
+/// the registry gives in the body, like `{"errors":[{"detail":"..."}]}`.
+///
+/// When the body doesn't look like that, the error only has the status.
+/// The `reqwest::Error` stays the root cause either way, so we can still check the
+/// status on it.
+async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        errors: Vec<ErrorDetail>,
+    }
+    #[derive(Deserialize)]
+    struct ErrorDetail {
+        detail: String,
+    }
+
+    let err = match response.error_for_status_ref() {
+        Ok(_) => return Ok(response),
+        Err(err) => err,
+    };
+
+    let details: Vec<_> = match response.json::<ErrorResponse>().await {
+        Ok(body) => body.errors.into_iter().map(|error| error.detail).collect(),
+        Err(_) => Vec::new(),
+    };
+
+    if details.is_empty() {
+        Err(err.into())
+    } else {
+        Err(anyhow::Error::from(err).context(format!("registry API error: {}", details.join("; "))))
+    }
+}
+
+/// `CrateBlocked` when the registry refused to serve the crate, otherwise the error itself.
+fn blocked_or(err: anyhow::Error, name: &str) -> anyhow::Error {
+    match err
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|err| err.status())
+    {
+        Some(status @ (StatusCode::FORBIDDEN | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS)) => {
+            CrateBlocked {
+                name: name.to_owned(),
+                status,
+            }
+            .into()
+        }
+        _ => err,
+    }
+}
+
+/// The URL of a crate's file in a sparse index, following cargo's directory layout:
+/// `1/a`, `2/ab`, `3/a/abc` and `ab/cd/abcd…`.
+fn sparse_index_url(index: &Url, name: &str) -> Result<Url> {
+    let name = name.to_lowercase();
+    let path = match name.len() {
+        0 => return Err(anyhow!("empty crate name")),
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    };
+
+    let mut url = index.clone();
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("sparse index URL can't be a base: {index}"))?
+        .pop_if_empty()
+        .extend(path.split('/'));
+    Ok(url)
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{async_wrapper, wrapper, TestEnvironment};
+    use futures_util::future::join_all;
+    use serde_json::json;
+    use test_case::test_case;
+    use tracing::instrument::WithSubscriber as _;
+
+    /// Log output of a test, collected by the subscriber from `Captured::subscriber`.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Captured {
+        fn subscriber(&self) -> impl tracing::Subscriber + Send + Sync {
+            let captured = self.clone();
+            tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_max_level(tracing::Level::INFO)
+                .with_writer(move || captured.clone())
+                .finish()
+        }
+
+        fn output(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Start a mock registry and point the registry API of `env` to it.
+    ///
+    /// `configure` can change more of the config before the API is created.
+    async fn mock_registry(
+        env: &TestEnvironment,
+        configure: impl FnOnce(&mut Config),
+    ) -> (mockito::ServerGuard, Arc<RegistryApi>) {
+        let crates_io = mockito::Server::new_async().await;
+        env.override_config(|config| {
+            config.registry_api_host = crates_io.url().parse().unwrap();
+            configure(config);
+        });
+        (crates_io, env.registry_api())
+    }
+
+    /// Mock the owners endpoint of `name`, with `users` as the owners.
+    ///
+    /// The mock isn't created yet, so tests can add expectations first.
+    fn mock_owners(
+        crates_io: &mut mockito::ServerGuard,
+        name: &str,
+        users: serde_json::Value,
+    ) -> mockito::Mock {
+        crates_io
+            .mock("GET", format!("/api/v1/crates/{name}/owners").as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "users": users }).to_string())
+    }
+
+    async fn mock_crate_metadata(
+        crates_io: &mut mockito::ServerGuard,
+        name: &str,
+    ) -> mockito::Mock {
+        crates_io
+            .mock("GET", format!("/api/v1/crates/{name}").as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "crate": { "id": name, "name": name } }).to_string())
+            .create_async()
+            .await
+    }
+
+    #[test]
+    fn get_crate_data_parses_owners() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _metadata = mock_crate_metadata(&mut crates_io, "foo").await;
+            let _m = mock_owners(
+                &mut crates_io,
+                "foo",
+                json!([
+                    {
+                        "avatar": "https://example.org/ghost.png",
+                        "login": "ghost",
+                        "kind": "user",
+                    },
+                ]),
+            )
+            .create_async()
+            .await;
+
+            let data = api.get_crate_data("foo").await?;
+
+            assert_eq!(data.owners.len(), 1);
+            assert_eq!(data.owners[0].login, "ghost");
+            assert_eq!(data.owners[0].avatar, "https://example.org/ghost.png");
+            assert_eq!(data.owners[0].kind, OwnerKind::User);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crates_data_fetches_all_crates() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let mut mocks = Vec::new();
+            for name in ["foo", "bar", "baz"] {
+                mocks.push(mock_crate_metadata(&mut crates_io, name).await);
+                mocks.push(
+                    mock_owners(
+                        &mut crates_io,
+                        name,
+                        json!([
+                            {
+                                "avatar": "https://example.org/ghost.png",
+                                "login": format!("{name}-owner"),
+                                "kind": "user",
+                            },
+                        ]),
+                    )
+                    .create_async()
+                    .await,
+                );
+            }
+            // unknown crates are skipped
+            mocks.push(
+                crates_io
+                    .mock("GET", "/api/v1/crates/unknown/owners")
+                    .with_status(404)
+                    .create_async()
+                    .await,
+            );
+
+            let mut data = api.get_crates_data(&["foo", "unknown", "bar", "baz"]).await;
+            data.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            assert_eq!(
+                data.iter()
+                    .map(|(name, data)| (name.as_str(), data.owners[0].login.as_str()))
+                    .collect::<Vec<_>>(),
+                [
+                    ("bar", "bar-owner"),
+                    ("baz", "baz-owner"),
+                    ("foo", "foo-owner")
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn warm_owner_cache() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+                config.crates_io_crate_data_cache_ttl = Duration::from_secs(60);
+                config.crates_io_crate_data_cache_size = 10;
+            })
+            .await;
+
+            let mut mocks = Vec::new();
+            for name in ["foo", "bar"] {
+                mocks.push(
+                    crates_io
+                        .mock("GET", format!("/api/v1/crates/{name}").as_str())
+                        .with_status(200)
+                        .with_header("content-type", "application/json")
+                        .with_body(json!({ "crate": { "id": name, "name": name } }).to_string())
+                        .expect(1)
+                        .create_async()
+                        .await,
+                );
+                mocks.push(
+                    mock_owners(
+                        &mut crates_io,
+                        name,
+                        json!([
+                            {
+                                "avatar": "https://example.org/ghost.png",
+                                "login": format!("{name}-owner"),
+                                "kind": "user",
+                            },
+                        ]),
+                    )
+                    .expect(1)
+                    .create_async()
+                    .await,
+                );
+            }
+            let _unknown = crates_io
+                .mock("GET", "/api/v1/crates/unknown/owners")
+                .with_status(404)
+                .create_async()
+                .await;
+
+            let report = api.warm_owner_cache(&["foo", "unknown", "bar"]).await;
+            assert_eq!(
+                report,
+                CacheWarmingReport {
+                    succeeded: 2,
+                    failed: 1,
+                }
+            );
+
+            // served from the cache, the registry is only asked once per crate
+            assert_eq!(
+                api.get_crate_data("foo").await?.owners[0].login,
+                "foo-owner"
+            );
+            assert_eq!(
+                api.get_crate_data("bar").await?.owners[0].login,
+                "bar-owner"
+            );
+            for mock in mocks {
+                mock.assert_async().await;
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_parses_teams() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _metadata = mock_crate_metadata(&mut crates_io, "foo").await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "users": [
+                            {
+                                "avatar": "https://example.org/ghost.png",
+                                "login": "ghost",
+                                "kind": "user",
+                            },
+                        ],
+                        "teams": [
+                            {
+                                "avatar": "https://example.org/rust-lang.png",
+                                "login": "github:rust-lang:owners",
+                            },
+                            {
+                                "login": "",
+                            },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("foo").await?;
+
+            assert_eq!(data.owners.len(), 2);
+            assert_eq!(data.owners[0].login, "ghost");
+            assert_eq!(data.owners[0].kind, OwnerKind::User);
+            assert_eq!(data.owners[1].login, "github:rust-lang:owners");
+            assert_eq!(data.owners[1].avatar, "https://example.org/rust-lang.png");
+            assert_eq!(data.owners[1].kind, OwnerKind::Team);
+
+            Ok(())
+        });
+    }
+
+    async fn mock_owner_avatars(crates_io: &mut mockito::ServerGuard) -> mockito::Mock {
+        mock_owners(
+            crates_io,
+            "foo",
+            json!([
+                { "login": "secure", "avatar": "https://example.org/secure.png" },
+                { "login": "insecure", "avatar": "http://example.org/insecure.png" },
+                { "login": "invalid", "avatar": "javascript:alert(1)" },
+                { "login": "missing", "avatar": null },
+            ]),
+        )
+        .create_async()
+        .await
+    }
+
+    #[test]
+    fn owner_avatars_are_validated() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+            let _m = mock_owner_avatars(&mut crates_io).await;
+
+            let avatars: Vec<_> = api
+                .get_owners("foo")
+                .await?
+                .into_iter()
+                .map(|owner| (owner.login, owner.avatar))
+                .collect();
+
+            assert_eq!(
+                avatars,
+                [
+                    ("secure".into(), "https://example.org/secure.png".into()),
+                    ("insecure".into(), DEFAULT_AVATAR.into()),
+                    ("invalid".into(), DEFAULT_AVATAR.into()),
+                    ("missing".into(), DEFAULT_AVATAR.into()),
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn owner_avatars_go_through_proxy() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.registry_avatar_proxy = Some("https://images.example.com/?url=".into());
+            })
+            .await;
+            let _m = mock_owner_avatars(&mut crates_io).await;
+
+            let avatars: Vec<_> = api
+                .get_owners("foo")
+                .await?
+                .into_iter()
+                .map(|owner| owner.avatar)
+                .collect();
+
+            assert_eq!(
+                avatars,
+                [
+                    "https://images.example.com/?url=https%3A%2F%2Fexample%2Eorg%2Fsecure%2Epng",
+                    "https://images.example.com/?url=http%3A%2F%2Fexample%2Eorg%2Finsecure%2Epng",
+                    DEFAULT_AVATAR,
+                    DEFAULT_AVATAR,
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn default_api_base_is_crates_io() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.registry_api_host = DEFAULT_API_BASE.parse().unwrap();
+                config.registry_api_offline = false;
+            });
+            let api = RegistryApi::new(&env.config())?;
+            assert!(!api.offline);
+            assert_eq!(api.api_base, Url::parse(DEFAULT_API_BASE)?);
+            assert_eq!(api.api_base.as_str(), "https://crates.io/");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn custom_layout() {
+        async_wrapper(|env| async move {
+            let mut crates_io = mockito::Server::new_async().await;
+            env.override_config(|config| {
+                config.registry_api_host =
+                    format!("{}/registry/", crates_io.url()).parse().unwrap();
+                config.registry_api_crate_path = "packages/{name}/api".into();
+            });
+
+            let _metadata = crates_io
+                .mock("GET", "/registry/packages/foo/api")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(json!({ "crate": { "name": "foo" } }).to_string())
+                .create_async()
+                .await;
+            let m = crates_io
+                .mock("GET", "/registry/packages/foo/api/owners")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "users": [
+                            { "login": "ghost" },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let data = env.registry_api().get_crate_data("foo").await?;
+
+            m.assert_async().await;
+            assert_eq!(data.owners.len(), 1);
+            assert_eq!(data.owners[0].login, "ghost");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn layout_builds_crate_urls() {
+        let base: Url = "https://crates.io".parse().unwrap();
+
+        assert_eq!(
+            RegistryApiLayout::default()
+                .crate_url(&base, "foo", &["owners"])
+                .unwrap()
+                .as_str(),
+            "https://crates.io/api/v1/crates/foo/owners"
+        );
+        assert_eq!(
+            RegistryApiLayout::from_template("/v2/{name}/")
+                .unwrap()
+                .crate_url(&base, "foo", &[])
+                .unwrap()
+                .as_str(),
+            "https://crates.io/v2/foo"
+        );
+        assert!(RegistryApiLayout::from_template("api/v1/crates").is_err());
+    }
+
+    #[test]
+    fn custom_user_agent() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.registry_api_user_agent = Some("docs.rs mirror (ops@example.org)".into());
+            })
+            .await;
+
+            let _metadata = mock_crate_metadata(&mut crates_io, "foo").await;
+            let m = mock_owners(&mut crates_io, "foo", json!([]))
+                .match_header("user-agent", "docs.rs mirror (ops@example.org)")
+                .expect(1)
+                .create_async()
+                .await;
+
+            api.get_crate_data("foo").await?;
+            m.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn invalid_user_agent() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.registry_api_user_agent = Some("docs.rs\nmirror".into());
+            });
+
+            assert!(RegistryApi::new(&env.config()).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_parses_metadata() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _owners = mock_owners(&mut crates_io, "serde", json!([]))
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/serde")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "categories": [
+                            {
+                                "category": "Encoding",
+                                "crates_cnt": 1234,
+                                "created_at": "2017-01-17T19:13:05.112025+00:00",
+                                "description": "Encoding and/or decoding data from one data format to another.",
+                                "id": "encoding",
+                                "slug": "encoding",
+                            },
+                        ],
+                        "crate": {
+                            "badges": [],
+                            "categories": ["encoding", "no-std"],
+                            "created_at": "2014-12-05T20:20:39.487502+00:00",
+                            "description": "A generic serialization/deserialization framework",
+                            "documentation": "https://docs.rs/serde",
+                            "downloads": 123456789,
+                            "exact_match": false,
+                            "homepage": "https://serde.rs",
+                            "id": "serde",
+                            "keywords": ["serde", "serialization", "no_std"],
+                            "max_stable_version": "1.0.203",
+                            "max_version": "1.0.203",
+                            "name": "serde",
+                            "newest_version": "1.0.203",
+                            "recent_downloads": 12345678,
+                            "repository": "https://github.com/serde-rs/serde",
+                            "updated_at": "2024-05-25T22:24:07.384431+00:00",
+                            "versions": [1, 2, 3],
+                        },
+                        "keywords": [
+                            {
+                                "crates_cnt": 1234,
+                                "created_at": "2015-01-28T19:15:40.536489+00:00",
+                                "id": "serde",
+                                "keyword": "serde",
+                            },
+                        ],
+                        "versions": [],
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("serde").await?;
+
+            assert_eq!(
+                data.description.as_deref(),
+                Some("A generic serialization/deserialization framework")
+            );
+            let keyword_slugs: Vec<_> = data.keywords.iter().map(|k| k.slug.as_str()).collect();
+            assert_eq!(keyword_slugs, ["serde", "serialization", "no_std"]);
+            let category_slugs: Vec<_> = data.categories.iter().map(|c| c.slug.as_str()).collect();
+            assert_eq!(category_slugs, ["encoding", "no-std"]);
+            assert_eq!(
+                data.repository.as_deref(),
+                Some("https://github.com/serde-rs/serde")
+            );
+            assert_eq!(data.homepage.as_deref(), Some("https://serde.rs"));
+            assert_eq!(data.documentation.as_deref(), Some("https://docs.rs/serde"));
+            assert_eq!(data.total_downloads, 123456789);
+            assert_eq!(data.recent_downloads, Some(12345678));
+            assert_eq!(data.max_version, Some(Version::new(1, 0, 203)));
+            assert_eq!(data.max_stable_version, Some(Version::new(1, 0, 203)));
+            assert_eq!(data.num_versions, Some(3));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_parses_keywords_and_categories() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _owners = mock_owners(&mut crates_io, "foo", json!([]))
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "categories": [
+                            {
+                                "category": "Command line utilities",
+                                "description": "Applications to run at the command line.",
+                                "id": "command-line-utilities",
+                                "slug": "command-line-utilities",
+                            },
+                            {
+                                "category": "Parser implementations",
+                                "description": "Parsers implemented for particular formats or languages.",
+                                "id": "parser-implementations",
+                                "slug": "parser-implementations",
+                            },
+                        ],
+                        "crate": {
+                            "name": "foo",
+                            "categories": ["parser-implementations", "command-line-utilities"],
+                            "keywords": ["cli", "parser", "no-details"],
+                        },
+                        "keywords": [
+                            { "id": "cli", "keyword": "cli" },
+                            { "id": "parser", "keyword": "parser" },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("foo").await?;
+
+            assert_eq!(
+                data.keywords,
+                [
+                    Keyword {
+                        name: "cli".into(),
+                        slug: "cli".into(),
+                        description: None,
+                    },
+                    Keyword {
+                        name: "parser".into(),
+                        slug: "parser".into(),
+                        description: None,
+                    },
+                    Keyword {
+                        name: "no-details".into(),
+                        slug: "no-details".into(),
+                        description: None,
+                    },
+                ]
+            );
+            assert_eq!(
+                data.categories,
+                [
+                    Category {
+                        name: "Parser implementations".into(),
+                        slug: "parser-implementations".into(),
+                        description: Some(
+                            "Parsers implemented for particular formats or languages.".into()
+                        ),
+                    },
+                    Category {
+                        name: "Command line utilities".into(),
+                        slug: "command-line-utilities".into(),
+                        description: Some("Applications to run at the command line.".into()),
+                    },
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_invalid_keyword_details_are_ignored() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _owners = mock_owners(&mut crates_io, "foo", json!([]))
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crate": { "name": "foo", "keywords": ["cli"] },
+                        "keywords": "not a list",
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("foo").await?;
+
+            assert_eq!(data.keywords.len(), 1);
+            assert_eq!(data.keywords[0].slug, "cli");
+            assert!(data.categories.is_empty());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_without_stable_version() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _owners = mock_owners(&mut crates_io, "foo", json!([]))
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crate": {
+                            "name": "foo",
+                            "downloads": 42,
+                            "max_version": "0.2.0-beta.1",
+                            "max_stable_version": null,
+                            "versions": [2, 1],
+                        },
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("foo").await?;
+
+            assert_eq!(data.max_version, Some("0.2.0-beta.1".parse()?));
+            assert!(data.max_stable_version.is_none());
+            assert_eq!(data.num_versions, Some(2));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_missing_metadata() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _owners = mock_owners(&mut crates_io, "foo", json!([]))
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crate": {
+                            "name": "foo",
+                            "description": null,
+                            "keywords": null,
+                            "downloads": 42,
+                            "recent_downloads": null,
+                        },
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("foo").await?;
+
+            assert!(data.description.is_none());
+            assert!(data.keywords.is_empty());
+            assert!(data.categories.is_empty());
+            assert!(data.repository.is_none());
+            assert!(data.homepage.is_none());
+            assert!(data.documentation.is_none());
+            assert_eq!(data.total_downloads, 42);
+            assert!(data.recent_downloads.is_none());
+            assert!(data.max_version.is_none());
+            assert!(data.max_stable_version.is_none());
+            assert!(data.num_versions.is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_reverse_dependencies_follows_pages() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let first = crates_io
+                .mock("GET", "/api/v1/crates/foo/reverse_dependencies")
+                .match_query(mockito::Matcher::UrlEncoded("page".into(), "1".into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "dependencies": [
+                            { "crate_id": "foo", "version_id": 1, "kind": "normal", "req": "^1" },
+                            { "crate_id": "foo", "version_id": 2, "kind": "dev", "req": "^1" },
+                        ],
+                        "versions": [
+                            { "id": 1, "crate": "bar", "num": "0.1.0" },
+                            { "id": 2, "crate": "baz", "num": "2.0.0" },
+                        ],
+                        "meta": { "total": 3, "next_page": "?page=2&per_page=100" },
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+            let second = crates_io
+                .mock("GET", "/api/v1/crates/foo/reverse_dependencies")
+                .match_query(mockito::Matcher::UrlEncoded("page".into(), "2".into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "dependencies": [
+                            { "crate_id": "foo", "version_id": 3 },
+                        ],
+                        "versions": [
+                            { "id": 3, "crate": "qux", "num": "1.2.3" },
+                        ],
+                        "meta": { "total": 3, "next_page": null },
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let dependencies = api.get_reverse_dependencies("foo").await?;
+
+            first.assert_async().await;
+            second.assert_async().await;
+            assert_eq!(
+                dependencies,
+                vec![
+                    ReverseDependency {
+                        name: "bar".into(),
+                        version: "0.1.0".into(),
+                        kind: "normal".into(),
+                    },
+                    ReverseDependency {
+                        name: "baz".into(),
+                        version: "2.0.0".into(),
+                        kind: "dev".into(),
+                    },
+                    ReverseDependency {
+                        name: "qux".into(),
+                        version: "1.2.3".into(),
+                        kind: "normal".into(),
+                    },
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_reverse_dependencies_stops_when_a_page_repeats() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let page = crates_io
+                .mock("GET", "/api/v1/crates/foo/reverse_dependencies")
+                .match_query(mockito::Matcher::UrlEncoded("page".into(), "1".into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "dependencies": [{ "crate_id": "foo", "version_id": 1 }],
+                        "versions": [{ "id": 1, "crate": "bar", "num": "0.1.0" }],
+                        "meta": { "total": 1, "next_page": "?page=1&per_page=100" },
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let dependencies = api.get_reverse_dependencies("foo").await?;
+
+            page.assert_async().await;
+            assert_eq!(
+                dependencies,
+                vec![ReverseDependency {
+                    name: "bar".into(),
+                    version: "0.1.0".into(),
+                    kind: "normal".into(),
+                }]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_caches_owners() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_crate_data_cache_ttl = Duration::from_secs(60);
+            })
+            .await;
+
+            let _metadata = mock_crate_metadata(&mut crates_io, "foo").await;
+            let m = mock_owners(
+                &mut crates_io,
+                "foo",
+                json!([
+                    {
+                        "avatar": "https://example.org/ghost.png",
+                        "login": "ghost",
+                        "kind": "user",
+                    },
+                ]),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+            let first = api.get_crate_data("foo").await?;
+            let second = api.get_crate_data("foo").await?;
+
+            m.assert_async().await;
+            assert_eq!(first.owners.len(), 1);
+            assert_eq!(second.owners.len(), 1);
+            assert_eq!(second.owners[0].login, "ghost");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn cleared_crate_data_cache_fetches_again() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_crate_data_cache_ttl = Duration::from_secs(60);
+            })
+            .await;
+
+            let _metadata = mock_crate_metadata(&mut crates_io, "foo").await;
+            let m = mock_owners(&mut crates_io, "foo", json!([]))
+                .expect(2)
+                .create_async()
+                .await;
+
+            api.get_crate_data("foo").await?;
+            api.clear_crate_data_cache();
+            api.get_crate_data("foo").await?;
+
+            m.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn not_modified_reuses_previous_response() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_crate_data_cache_ttl = Duration::ZERO;
+            })
+            .await;
+
+            let _metadata = mock_crate_metadata(&mut crates_io, "foo").await;
+            let full = mock_owners(
+                &mut crates_io,
+                "foo",
+                json!([
+                    {
+                        "avatar": "https://example.org/ghost.png",
+                        "login": "ghost",
+                        "kind": "user",
+                    },
+                ]),
+            )
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_header("etag", "\"abc\"")
+            .expect(1)
+            .create_async()
+            .await;
+            let not_modified = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .match_header("if-none-match", "\"abc\"")
+                .with_status(304)
+                .expect(1)
+                .create_async()
+                .await;
+
+            api.get_crate_data("foo").await?;
+            let data = api.get_crate_data("foo").await?;
+
+            full.assert_async().await;
+            not_modified.assert_async().await;
+            assert_eq!(data.owners.len(), 1);
+            assert_eq!(data.owners[0].login, "ghost");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn crate_data_cache_evicts_oldest_entry() {
+        let cache = CrateDataCache::new(Duration::from_secs(60), 2);
+        cache.insert("a", CrateData::default());
+        cache.insert("b", CrateData::default());
+        cache.insert("c", CrateData::default());
+
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn circuit_breaker_lets_a_single_probe_through() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+        breaker.record_failure();
+        assert!(!breaker.allow_request());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(breaker.allow_request());
+        assert!(!breaker.allow_request());
+
+        breaker.record_success();
+        assert!(breaker.allow_request());
+        assert!(breaker.allow_request());
+    }
+
+    #[test]
+    fn crate_data_cache_expires_entries() {
+        let cache = CrateDataCache::new(Duration::from_millis(1), 10);
+        cache.insert("a", CrateData::default());
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn retries_on_server_errors() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 3;
+                config.crates_io_api_call_retry_delay = Duration::from_millis(1);
+            })
+            .await;
+
+            let failing = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(503)
+                .expect(2)
+                .create_async()
+                .await;
+            let succeeding = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "version": {
+                            "num": "1.0.0",
+                            "created_at": "2024-01-02T03:04:05Z",
+                            "yanked": true,
+                            "downloads": 42,
+                        },
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let data = api.get_release_data("foo", "1.0.0").await?;
+
+            failing.assert_async().await;
+            succeeding.assert_async().await;
+            assert_eq!(
+                data.release_time,
+                Some("2024-01-02T03:04:05Z".parse::<DateTime<Utc>>()?)
+            );
+            assert!(data.yanked);
+            assert_eq!(data.downloads, Some(42));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn requests_are_counted_in_metrics() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 1;
+                config.crates_io_api_call_retry_delay = Duration::from_millis(1);
+            })
+            .await;
+
+            let _owners = mock_owners(&mut crates_io, "foo", json!([]))
+                .create_async()
+                .await;
+            let _failing = crates_io
+                .mock("GET", "/api/v1/crates/bar/owners")
+                .with_status(500)
+                .expect(2)
+                .create_async()
+                .await;
+
+            api.get_owners("foo").await?;
+            assert!(api.get_owners("bar").await.is_err());
+
+            let metrics = env.instance_metrics();
+            let requests = |outcome| {
+                metrics
+                    .registry_api_requests
+                    .with_label_values(&["owners", outcome])
+                    .get()
+            };
+            assert_eq!(requests("success"), 1);
+            assert_eq!(requests("retry"), 1);
+            assert_eq!(requests("error"), 1);
+            assert_eq!(
+                metrics
+                    .registry_api_response_time
+                    .with_label_values(&["owners"])
+                    .get_sample_count(),
+                3
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn circuit_breaker_pauses_requests_after_failures() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+                config.crates_io_api_circuit_breaker_threshold = 2;
+                config.crates_io_api_circuit_breaker_cooldown = Duration::from_millis(200);
+            })
+            .await;
+
+            let failing = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(500)
+                .expect(2)
+                .create_async()
+                .await;
+
+            assert!(api.get_owners("foo").await.is_err());
+            assert!(api.get_owners("foo").await.is_err());
+
+            // the breaker is open, the registry isn't asked
+            assert!(api.get_owners("foo").await.is_err());
+            failing.assert_async().await;
+            failing.remove_async().await;
+
+            let succeeding = mock_owners(&mut crates_io, "foo", json!([]))
+                .expect(2)
+                .create_async()
+                .await;
+
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            api.get_owners("foo").await?;
+            // a success closes the breaker again
+            api.get_owners("foo").await?;
+            succeeding.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn circuit_breaker_can_be_disabled() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(60));
+        for _ in 0..100 {
+            breaker.record_failure();
+        }
+        assert!(breaker.allow_request());
+    }
+
+    #[test]
+    fn samples_logs_of_successful_requests() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+                config.crates_io_api_log_sample_rate = 3;
+            })
+            .await;
+
+            let _succeeding = mock_owners(&mut crates_io, "foo", json!([]))
+                .expect(7)
+                .create_async()
+                .await;
+            let _failing = crates_io
+                .mock("GET", "/api/v1/crates/bar/owners")
+                .with_status(500)
+                .expect(2)
+                .create_async()
+                .await;
+
+            let captured = Captured::default();
+
+            async {
+                for _ in 0..7 {
+                    api.get_owners("foo").await.unwrap();
+                }
+                assert!(api.get_owners("bar").await.is_err());
+                assert!(api.get_owners("bar").await.is_err());
+            }
+            .with_subscriber(captured.subscriber())
+            .await;
+
+            let logs = captured.output();
+            // the 1st, 4th and 7th success
+            assert_eq!(logs.matches("registry API request succeeded").count(), 3);
+            assert_eq!(logs.matches("registry API request failed").count(), 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn log_sampler() {
+        let sampler = LogSampler::new(2);
+        assert!(sampler.sample("crate"));
+        assert!(!sampler.sample("crate"));
+        // every endpoint is counted on its own
+        assert!(sampler.sample("owners"));
+        assert!(sampler.sample("crate"));
+
+        let disabled = LogSampler::new(0);
+        assert!(!disabled.sample("crate"));
+    }
+
+    #[test]
+    fn release_data_created_at() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+            })
+            .await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/valid/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "version": { "num": "1.0.0", "created_at": "2024-01-02T03:04:05Z" },
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/missing/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(json!({ "version": { "num": "1.0.0" } }).to_string())
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/malformed/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "version": { "num": "1.0.0", "created_at": "yesterday" },
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            assert_eq!(
+                api.get_release_data("valid", "1.0.0").await?.release_time,
+                Some("2024-01-02T03:04:05Z".parse::<DateTime<Utc>>()?)
+            );
+            assert_eq!(
+                api.get_release_data("missing", "1.0.0").await?.release_time,
+                None
+            );
+            assert!(api.get_release_data("malformed", "1.0.0").await.is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn release_data_server_error_is_propagated() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+            })
+            .await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(500)
+                .create_async()
+                .await;
+
+            assert!(api.get_release_data("foo", "1.0.0").await.is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn release_data_falls_back_to_sparse_index() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+                config.registry_sparse_index_fallback =
+                    Some(config.registry_api_host.join("index/").unwrap());
+            })
+            .await;
+
+            let _api = crates_io
+                .mock("GET", mockito::Matcher::Regex("^/api/v1/".into()))
+                .with_status(500)
+                .create_async()
+                .await;
+            let _index = crates_io
+                .mock("GET", "/index/3/f/foo")
+                .with_status(200)
+                .with_body(concat!(
+                    r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"","features":{},"yanked":true}"#,
+                    "\n",
+                    r#"{"name":"foo","vers":"1.1.0","deps":[],"cksum":"","features":{},"yanked":false}"#,
+                    "\n",
+                ))
+                .create_async()
+                .await;
+
+            let data = api.get_release_data("foo", "1.0.0").await?;
+            assert!(data.yanked);
+            assert_eq!(data.release_time, None);
+            assert_eq!(data.downloads, None);
+
+            let data = api.get_release_data("foo", "1.1.0").await?;
+            assert!(!data.yanked);
+
+            assert!(api.get_release_data("foo", "2.0.0").await.is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn sparse_index_url_layout() -> Result<()> {
+        let index: Url = "https://index.crates.io/".parse()?;
+        for (name, path) in [
+            ("a", "/1/a"),
+            ("ab", "/2/ab"),
+            ("abc", "/3/a/abc"),
+            ("Serde", "/se/rd/serde"),
+        ] {
+            assert_eq!(sparse_index_url(&index, name)?.path(), path);
+        }
+        assert!(sparse_index_url(&index, "").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn get_release_data_uses_version_endpoint() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let version = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "version": {
+                            "num": "1.0.0",
+                            "created_at": "2024-01-02T03:04:05Z",
+                            "yanked": false,
+                            "downloads": 7,
+                        },
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+            let versions = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .expect(0)
+                .create_async()
+                .await;
+
+            let data = api.get_release_data("foo", "1.0.0").await?;
+
+            version.assert_async().await;
+            versions.assert_async().await;
+            assert_eq!(
+                data.release_time,
+                Some("2024-01-02T03:04:05Z".parse::<DateTime<Utc>>()?)
+            );
+            assert!(!data.yanked);
+            assert_eq!(data.yank_reason, None);
+            assert_eq!(data.downloads, Some(7));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn compressed_responses_are_decompressed() {
+        async_wrapper(|env| async move {
+            use flate2::{write::GzEncoder, Compression};
+            use std::io::Write as _;
+
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(
+                json!({
+                    "versions": [
+                        { "num": "1.0.0", "created_at": "2024-01-01T00:00:00Z", "downloads": 10 },
+                    ],
+                })
+                .to_string()
+                .as_bytes(),
+            )?;
+
+            let m = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .match_header("accept-encoding", mockito::Matcher::Regex("gzip".into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_header("content-encoding", "gzip")
+                .with_body(encoder.finish()?)
+                .expect(1)
+                .create_async()
+                .await;
+
+            let data = api.get_all_release_data("foo").await?;
+
+            m.assert_async().await;
+            assert_eq!(data[&Version::new(1, 0, 0)].downloads, Some(10));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_all_release_data_with_single_request() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let m = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "versions": [
+                            {
+                                "num": "1.1.0",
+                                "created_at": "2024-02-01T00:00:00Z",
+                                "downloads": 20,
+                            },
+                            {
+                                "num": "1.0.0",
+                                "created_at": "2024-01-01T00:00:00Z",
+                                "yanked": true,
+                                "downloads": 10,
+                            },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let data = api.get_all_release_data("foo").await?;
+
+            m.assert_async().await;
+            assert_eq!(data.len(), 2);
+            let old = &data[&Version::new(1, 0, 0)];
+            assert!(old.yanked);
+            assert_eq!(old.downloads, Some(10));
+            assert_eq!(
+                old.release_time,
+                Some("2024-01-01T00:00:00Z".parse::<DateTime<Utc>>()?)
+            );
+            let new = &data[&Version::new(1, 1, 0)];
+            assert!(!new.yanked);
+            assert_eq!(new.downloads, Some(20));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn version_list_over_the_limit_is_rejected() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_max_versions = 100;
+            })
+            .await;
+
+            let versions: Vec<_> = (0..10_000)
+                .map(|patch| json!({ "num": format!("1.0.{patch}"), "downloads": 1 }))
+                .collect();
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(json!({ "versions": versions }).to_string())
+                .create_async()
+                .await;
+
+            let err = api.get_all_release_data("foo").await.unwrap_err();
+            assert!(
+                format!("{err:#}").contains("the crate has more than 100 versions"),
+                "{err:#}"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test_case(false; "with content length")]
+    #[test_case(true; "chunked")]
+    fn response_over_the_size_limit_is_rejected(chunked: bool) {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_max_response_size = 1024;
+            })
+            .await;
+
+            let versions: Vec<_> = (0..100)
+                .map(|patch| json!({ "num": format!("1.0.{patch}"), "downloads": 1 }))
+                .collect();
+            let body = json!({ "versions": versions }).to_string();
+            let mock = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .with_status(200)
+                .with_header("content-type", "application/json");
+            let _m = if chunked {
+                mock.with_chunked_body(move |w| w.write_all(body.as_bytes()))
+            } else {
+                mock.with_body(body)
+            }
+            .create_async()
+            .await;
+
+            let err = api.get_all_release_data("foo").await.unwrap_err();
+            assert!(
+                format!("{err:#}").contains("the registry response is larger than 1024 bytes"),
+                "{err:#}"
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn version_list_seed() {
+        let seed = VersionListSeed { max_versions: 2 };
+        let body = |count: usize| {
+            let versions: Vec<_> = (0..count)
+                .map(|patch| json!({ "num": format!("1.0.{patch}") }))
+                .collect();
+            json!({ "meta": { "total": count }, "versions": versions }).to_string()
+        };
+
+        let versions = parse_json_seed(body(2).as_bytes(), seed).unwrap();
+        assert_eq!(
+            versions
+                .iter()
+                .map(|v| v.num.to_string())
+                .collect::<Vec<_>>(),
+            ["1.0.0", "1.0.1"]
+        );
+        assert!(parse_json_seed(body(3).as_bytes(), seed).is_err());
+        assert!(parse_json_seed(br#"{"meta": {}}"#, seed).is_err());
+    }
+
+    #[test]
+    fn get_release_data_parses_yank_reason() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "version": {
+                            "num": "1.0.0",
+                            "created_at": "2024-01-02T03:04:05Z",
+                            "yanked": true,
+                            "yank_message": "security vulnerability",
+                            "downloads": 7,
+                        },
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let data = api.get_release_data("foo", "1.0.0").await?;
+
+            assert!(data.yanked);
+            assert_eq!(data.yank_reason.as_deref(), Some("security vulnerability"));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_release_data_falls_back_to_version_list() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let version = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(404)
+                .expect(1)
+                .create_async()
+                .await;
+            let versions = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "versions": [
+                            {
+                                "num": "1.1.0",
+                                "created_at": "2024-02-02T03:04:05Z",
+                                "yanked": false,
+                                "downloads": 1,
+                            },
+                            {
+                                "num": "1.0.0",
+                                "created_at": "2024-01-02T03:04:05Z",
+                                "yanked": true,
+                                "downloads": 7,
+                            },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            let data = api.get_release_data("foo", "1.0.0").await?;
+
+            version.assert_async().await;
+            versions.assert_async().await;
+            assert_eq!(
+                data.release_time,
+                Some("2024-01-02T03:04:05Z".parse::<DateTime<Utc>>()?)
+            );
+            assert!(data.yanked);
+            assert_eq!(data.downloads, Some(7));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn times_out_slow_requests() {
+        async_wrapper(|env| async move {
+            // accepts connections, but never answers
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+            let address = listener.local_addr()?;
+            tokio::spawn(async move {
+                let mut connections = Vec::new();
+                while let Ok((connection, _)) = listener.accept().await {
+                    connections.push(connection);
+                }
+            });
+
+            env.override_config(|config| {
+                config.registry_api_host = format!("http://{address}").parse().unwrap();
+                config.crates_io_api_call_retries = 0;
+                config.crates_io_api_call_timeout = Duration::from_millis(100);
+            });
+
+            let start = Instant::now();
+            assert!(env
+                .registry_api()
+                .get_release_data("foo", "1.0.0")
+                .await
+                .is_err());
+            assert!(start.elapsed() < Duration::from_secs(5));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn limits_concurrent_requests() {
+        async_wrapper(|env| async move {
+            use std::sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            };
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            // answers every request slowly, while counting how many are in flight
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+            let address = listener.local_addr()?;
+            let in_flight = Arc::new(AtomicUsize::new(0));
+            let max_in_flight = Arc::new(AtomicUsize::new(0));
+            tokio::spawn({
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                async move {
+                    while let Ok((mut connection, _)) = listener.accept().await {
+                        let in_flight = in_flight.clone();
+                        let max_in_flight = max_in_flight.clone();
+                        tokio::spawn(async move {
+                            let mut request = Vec::new();
+                            let mut buffer = [0; 1024];
+                            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                                let read = connection.read(&mut buffer).await.unwrap();
+                                if read == 0 {
+                                    return;
+                                }
+                                request.extend_from_slice(&buffer[..read]);
+                            }
+
+                            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                            max_in_flight.fetch_max(current, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                            let body = json!({ "version": { "num": "1.0.0" } }).to_string();
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\n\
+                                 content-type: application/json\r\n\
+                                 content-length: {}\r\n\
+                                 connection: close\r\n\r\n{body}",
+                                body.len()
+                            );
+                            connection.write_all(response.as_bytes()).await.unwrap();
+                        });
+                    }
+                }
+            });
+
+            env.override_config(|config| {
+                config.registry_api_host = format!("http://{address}").parse().unwrap();
+                config.crates_io_api_max_concurrent_requests = 2;
+            });
+
+            let api = env.registry_api();
+            let results = join_all((0..6).map(|_| api.get_release_data("foo", "1.0.0"))).await;
+
+            assert!(results.iter().all(|result| result.is_ok()));
+            assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn respects_min_request_interval() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_min_request_interval = Duration::from_millis(100);
+            })
+            .await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(json!({ "version": { "num": "1.0.0" } }).to_string())
+                .expect(3)
+                .create_async()
+                .await;
+
+            let start = Instant::now();
+            for _ in 0..3 {
+                api.get_release_data("foo", "1.0.0").await?;
+            }
+            assert!(start.elapsed() >= Duration::from_millis(200));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn disk_cache_is_used_without_registry() {
+        async_wrapper(|env| async move {
+            let cache_dir = tempfile::tempdir()?;
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_disk_cache_path = Some(cache_dir.path().to_owned());
+                config.crates_io_api_call_retries = 0;
+            })
+            .await;
+
+            let m = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "version": {
+                            "num": "1.0.0",
+                            "created_at": "2024-01-02T03:04:05Z",
+                            "downloads": 7,
+                        },
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create_async()
+                .await;
+
+            api.get_release_data("foo", "1.0.0").await?;
+            m.assert_async().await;
+            m.remove_async().await;
+
+            // a new client, so nothing is cached in memory
+            let api = RegistryApi::new(&env.config())?;
+            let data = api.get_release_data("foo", "1.0.0").await?;
+            assert_eq!(data.downloads, Some(7));
+            assert_eq!(
+                data.release_time,
+                Some("2024-01-02T03:04:05Z".parse::<DateTime<Utc>>()?)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn requests_are_traced() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+            })
+            .await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(json!({ "version": { "num": "1.0.0" } }).to_string())
+                .create_async()
+                .await;
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/bar/owners")
+                .with_status(403)
+                .create_async()
+                .await;
+
+            let captured = Captured::default();
+
+            async {
+                api.get_release_data("foo", "1.0.0").await.unwrap();
+                assert!(api.get_crate_data("bar").await.is_err());
+            }
+            .with_subscriber(captured.subscriber())
+            .await;
+
+            let output = captured.output();
+            let success = output
+                .lines()
+                .find(|line| line.contains("registry API request succeeded"))
+                .unwrap();
+            assert!(success.contains("name=\"foo\""));
+            assert!(success.contains("status=200"));
+            let failure = output
+                .lines()
+                .find(|line| line.contains("registry API request failed"))
+                .unwrap();
+            assert!(failure.contains(" WARN "));
+            assert!(failure.contains("name=\"bar\""));
+            assert!(failure.contains("status=403"));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn requests_go_through_configured_proxy() {
+        async_wrapper(|env| async move {
+            let mut proxy = mockito::Server::new_async().await;
+            env.override_config(|config| {
+                // not resolvable, so the request can only succeed through the proxy
+                config.registry_api_host = "http://registry.invalid".parse().unwrap();
+                config.registry_api_proxy = Some(proxy.url().parse().unwrap());
+                config.crates_io_api_call_retries = 0;
+            });
+
+            let m = mock_owners(
+                &mut proxy,
+                "foo",
+                json!([{ "avatar": null, "login": "foo", "name": null }]),
+            )
+            .match_header("host", "registry.invalid")
+            .expect(1)
+            .create_async()
+            .await;
+
+            let owners = env.registry_api().get_owners("foo").await?;
+
+            m.assert_async().await;
+            assert_eq!(owners.len(), 1);
+            assert_eq!(owners[0].login, "foo");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn proxy_is_skipped_for_no_proxy_hosts() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                // nothing listens here
+                config.registry_api_proxy = Some("http://127.0.0.1:9".parse().unwrap());
+                config.registry_api_no_proxy = Some("127.0.0.1".into());
+                config.crates_io_api_call_retries = 0;
+            })
+            .await;
+
+            let m = mock_owners(&mut crates_io, "foo", json!([]))
+                .expect(1)
+                .create_async()
+                .await;
+
+            api.get_owners("foo").await?;
+
+            m.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn connection_pool_limits_from_config() {
+        wrapper(|env| {
+            assert_eq!(
+                ConnectionPoolLimits::from_config(&env.config()),
+                ConnectionPoolLimits {
+                    max_idle_per_host: None,
+                    idle_timeout: None,
+                }
+            );
+
+            env.override_config(|config| {
+                config.crates_io_api_pool_max_idle_per_host = Some(1);
+                config.crates_io_api_pool_idle_timeout = Some(Duration::from_secs(5));
+            });
+            assert_eq!(
+                ConnectionPoolLimits::from_config(&env.config()),
+                ConnectionPoolLimits {
+                    max_idle_per_host: Some(1),
+                    idle_timeout: Some(Duration::from_secs(5)),
+                }
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn requests_succeed_with_tiny_connection_pool() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_pool_max_idle_per_host = Some(0);
+                config.crates_io_api_pool_idle_timeout = Some(Duration::from_millis(1));
+            })
+            .await;
+
+            let m = mock_owners(&mut crates_io, "foo", json!([]))
+                .expect(3)
+                .create_async()
+                .await;
+
+            for _ in 0..3 {
+                api.get_owners("foo").await?;
+            }
+
+            m.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn ping() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let m = crates_io
+                .mock("HEAD", "/")
+                .with_status(200)
+                .expect(1)
+                .create_async()
+                .await;
+
+            api.ping().await?;
+            m.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn offline_sends_no_requests() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.registry_api_offline = true;
+            })
+            .await;
+
+            let m = crates_io
+                .mock("GET", mockito::Matcher::Any)
+                .expect(0)
+                .create_async()
+                .await;
+            let ping = crates_io.mock("HEAD", "/").expect(0).create_async().await;
+
+            assert!(api.get_crate_data("foo").await.is_err());
+            assert!(api.ping().await.is_err());
+            m.assert_async().await;
+            ping.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn ping_fails_when_registry_is_down() {
+        async_wrapper(|env| async move {
+            let crates_io = mockito::Server::new_async().await;
+            let url = crates_io.url();
+            drop(crates_io);
+            env.override_config(|config| {
+                config.registry_api_host = url.parse().unwrap();
+            });
+
+            assert!(env.registry_api().ping().await.is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 3;
+                config.crates_io_api_call_retry_delay = Duration::from_millis(1);
+            })
+            .await;
+
+            let not_found = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(404)
+                .expect(1)
+                .create_async()
+                .await;
+
+            assert!(api.get_crate_data("foo").await.is_err());
+            not_found.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 1;
+                config.crates_io_api_max_retry_delay = Duration::from_millis(1);
+            })
+            .await;
+
+            let limited = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(429)
+                .with_header("retry-after", "3600")
+                .expect(1)
+                .create_async()
+                .await;
+            let succeeding = mock_owners(&mut crates_io, "foo", json!([]))
+                .expect(1)
+                .create_async()
+                .await;
+
+            tokio::time::timeout(Duration::from_secs(10), api.get_owners("foo")).await??;
+
+            limited.assert_async().await;
+            succeeding.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn etag_cache_evicts_the_oldest_entry() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_etag_cache_size = 2;
+            })
+            .await;
+
+            for name in ["foo", "bar", "baz"] {
+                mock_owners(&mut crates_io, name, json!([]))
+                    .with_header("etag", &format!("\"{name}\""))
+                    .create_async()
+                    .await;
+            }
+
+            for name in ["foo", "bar", "baz"] {
+                api.get_owners(name).await?;
+            }
+
+            let etag_cache = api.etag_cache.lock().unwrap();
+            let mut cached: Vec<_> = etag_cache.values().map(|(_, etag, _)| etag).collect();
+            cached.sort();
+            assert_eq!(cached, ["\"bar\"", "\"baz\""]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn get_crate_data_keeps_owners_when_metadata_fails() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.crates_io_api_call_retries = 0;
+            })
+            .await;
+
+            let _owners = mock_owners(
+                &mut crates_io,
+                "foo",
+                json!([{ "login": "ghost", "kind": "user" }]),
+            )
+            .create_async()
+            .await;
+            let metadata = crates_io
+                .mock("GET", "/api/v1/crates/foo")
+                .with_status(500)
+                .expect(2)
+                .create_async()
+                .await;
+
+            let data = api.get_crate_data("foo").await?;
+            assert_eq!(data.owners.len(), 1);
+            assert_eq!(data.owners[0].login, "ghost");
+            assert!(data.description.is_none());
+            assert_eq!(data.total_downloads, 0);
+
+            // partial data isn't cached
+            api.get_crate_data("foo").await?;
+            metadata.assert_async().await;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn error_details_from_the_registry() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(404)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "errors": [
+                            { "detail": "crate `foo` does not exist" },
+                            { "detail": "try again later" },
+                        ]
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let err = api.get_owners("foo").await.unwrap_err();
+            assert!(format!("{err:#}")
+                .contains("registry API error: crate `foo` does not exist; try again later"));
+            // the status is still there for us to check
+            assert_eq!(
+                err.downcast_ref::<reqwest::Error>()
+                    .and_then(|err| err.status()),
+                Some(StatusCode::NOT_FOUND)
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test_case("not json"; "text")]
+    #[test_case(r#"{"message": "nope"}"#; "other json")]
+    #[test_case(r#"{"errors": []}"#; "no details")]
+    fn error_without_details_from_the_registry(body: &str) {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(404)
+                .with_body(body)
+                .create_async()
+                .await;
+
+            let err = api.get_owners("foo").await.unwrap_err();
+            assert!(!format!("{err:#}").contains("registry API error"));
+            assert!(format!("{err:#}").contains("404"));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn owners_of_blocked_crate() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |_| {}).await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(451)
+                .create_async()
+                .await;
+
+            let err = api.get_owners("foo").await.unwrap_err();
+            let blocked = err
+                .downcast_ref::<CrateBlocked>()
+                .expect("expected a CrateBlocked error");
+            assert_eq!(blocked.name, "foo");
+            assert_eq!(blocked.status, StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn release_data_of_blocked_crate() {
+        async_wrapper(|env| async move {
+            let (mut crates_io, api) = mock_registry(&env, |config| {
+                config.registry_sparse_index_fallback =
+                    Some(config.registry_api_host.join("index/").unwrap());
+            })
+            .await;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates/foo/1.0.0")
+                .with_status(403)
+                .create_async()
+                .await;
+            let index = crates_io
+                .mock("GET", "/index/3/f/foo")
+                .expect(0)
+                .create_async()
+                .await;
+
+            let err = api.get_release_data("foo", "1.0.0").await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<CrateBlocked>().map(|err| err.status),
+                Some(StatusCode::FORBIDDEN)
+            );
+            index.assert_async().await;
+
+            Ok(())
+        });
+    }
 }
@@ -35,7 +35,7 @@ impl DatabaseBackend {
         .await?
         {
             Some(public) => Ok(public),
-            None => Err(super::PathNotFoundError.into()),
+            None => Err(super::PathNotFoundError::new(path).into()),
         }
     }
 
@@ -54,7 +54,7 @@ impl DatabaseBackend {
         {
             Ok(())
         } else {
-            Err(super::PathNotFoundError.into())
+            Err(super::PathNotFoundError::new(path).into())
         }
     }
 
@@ -102,7 +102,7 @@ impl DatabaseBackend {
             )
             .fetch_optional(&self.pool)
             .await?
-            .ok_or(super::PathNotFoundError)?
+            .ok_or_else(|| super::PathNotFoundError::new(path))?
         } else {
             // The size limit is checked at the database level, to avoid receiving data altogether if
             // the limit is exceeded.
@@ -119,7 +119,7 @@ impl DatabaseBackend {
             )
             .fetch_optional(&self.pool)
             .await?
-            .ok_or(super::PathNotFoundError)?
+            .ok_or_else(|| super::PathNotFoundError::new(path))?
         };
 
         if result.is_too_big {
@@ -26,9 +26,26 @@ use tracing::{error, info_span, instrument, trace};
 
 type FileRange = RangeInclusive<u64>;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Default, thiserror::Error)]
 #[error("path not found")]
-pub(crate) struct PathNotFoundError;
+pub(crate) struct PathNotFoundError {
+    /// the path we tried to access, when we know it.
+    pub(crate) path: Option<String>,
+}
+
+impl PathNotFoundError {
+    pub(crate) fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+}
+
+/// we couldn't reach the storage backend, or it didn't answer in time.
+/// Unlike other storage errors, this is not a bug in docs.rs.
+#[derive(Debug, thiserror::Error)]
+#[error("storage backend unavailable")]
+pub(crate) struct StorageUnavailableError(#[source] pub(crate) anyhow::Error);
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) struct Blob {
@@ -356,7 +373,7 @@ impl AsyncStorage {
             let path = path.to_owned();
             spawn_blocking(move || archive_index::find_in_file(index_filename, &path)).await
         }?
-        .ok_or(PathNotFoundError)?;
+        .ok_or_else(|| PathNotFoundError::new(path))?;
 
         let blob = self
             .get_range(
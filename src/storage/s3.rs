@@ -40,29 +40,36 @@ static NOT_FOUND_ERROR_CODES: [&str; 5] = [
 ];
 
 trait S3ResultExt<T> {
-    fn convert_errors(self) -> anyhow::Result<T>;
+    fn convert_errors(self, path: &str) -> anyhow::Result<T>;
 }
 
 impl<T, E> S3ResultExt<T> for Result<T, SdkError<E>>
 where
     E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
 {
-    fn convert_errors(self) -> anyhow::Result<T> {
+    fn convert_errors(self, path: &str) -> anyhow::Result<T> {
         match self {
             Ok(result) => Ok(result),
             Err(err) => {
                 if let Some(err_code) = err.code() {
                     if NOT_FOUND_ERROR_CODES.iter().any(|&code| err_code == code) {
-                        return Err(super::PathNotFoundError.into());
+                        return Err(super::PathNotFoundError::new(path).into());
                     }
                 }
 
                 if let SdkError::ServiceError(err) = &err {
                     if err.raw().status().as_u16() == http::StatusCode::NOT_FOUND.as_u16() {
-                        return Err(super::PathNotFoundError.into());
+                        return Err(super::PathNotFoundError::new(path).into());
                     }
                 }
 
+                if matches!(
+                    err,
+                    SdkError::DispatchFailure(_) | SdkError::TimeoutError(_)
+                ) {
+                    return Err(super::StorageUnavailableError(err.into()).into());
+                }
+
                 Err(err.into())
             }
         }
@@ -123,7 +130,7 @@ impl S3Backend {
             .key(path)
             .send()
             .await
-            .convert_errors()
+            .convert_errors(path)
         {
             Ok(_) => Ok(true),
             Err(err) if err.is::<super::PathNotFoundError>() => Ok(false),
@@ -139,7 +146,7 @@ impl S3Backend {
             .key(path)
             .send()
             .await
-            .convert_errors()?
+            .convert_errors(path)?
             .tag_set()
             .iter()
             .filter(|tag| tag.key() == PUBLIC_ACCESS_TAG)
@@ -170,7 +177,7 @@ impl S3Backend {
             })
             .send()
             .await
-            .convert_errors()
+            .convert_errors(path)
             .map(|_| ())
     }
 
@@ -188,7 +195,7 @@ impl S3Backend {
             .set_range(range.map(|r| format!("bytes={}-{}", r.start(), r.end())))
             .send()
             .await
-            .convert_errors()?;
+            .convert_errors(path)?;
 
         let mut content = crate::utils::sized_buffer::SizedBuffer::new(max_size);
         content.reserve(
@@ -129,11 +129,12 @@ impl<'a> FakeRelease<'a> {
             rustdoc_files: Vec::new(),
             doc_targets: Vec::new(),
             default_target: None,
-            registry_crate_data: CrateData { owners: Vec::new() },
+            registry_crate_data: CrateData::default(),
             registry_release_data: ReleaseData {
-                release_time: Utc::now(),
+                release_time: Some(Utc::now()),
                 yanked: false,
-                downloads: 0,
+                yank_reason: None,
+                downloads: Some(0),
             },
             has_docs: true,
             has_examples: false,
@@ -156,7 +157,7 @@ impl<'a> FakeRelease<'a> {
     }
 
     pub(crate) fn release_time(mut self, new: DateTime<Utc>) -> Self {
-        self.registry_release_data.release_time = new;
+        self.registry_release_data.release_time = Some(new);
         self
     }
 
@@ -522,7 +523,7 @@ impl<'a> FakeRelease<'a> {
             default_target,
             source_meta,
             self.doc_targets,
-            &self.registry_release_data,
+            Some(&self.registry_release_data),
             self.has_docs,
             self.has_examples,
             algs,
@@ -83,18 +83,6 @@ where
     }
 }
 
-/// check a request if the cache control header matches NoCache
-pub(crate) fn assert_no_cache(res: &Response) {
-    assert_eq!(
-        res.headers()
-            .get("Cache-Control")
-            .expect("missing cache-control header")
-            .to_str()
-            .unwrap(),
-        cache::NO_CACHING.to_str().unwrap(),
-    );
-}
-
 /// check a request if the cache control header matches the given cache config.
 pub(crate) fn assert_cache_control(
     res: &Response,
@@ -143,8 +131,16 @@ pub(crate) fn assert_success_cached(
 pub(crate) fn assert_not_found(path: &str, web: &TestFrontend) -> Result<()> {
     let response = web.get(path).send()?;
 
-    // for now, 404s should always have `no-cache`
-    assert_no_cache(&response);
+    // 404s are cached for a short time
+    assert_eq!(
+        response
+            .headers()
+            .get("Cache-Control")
+            .expect("missing cache-control header")
+            .to_str()
+            .unwrap(),
+        cache::SHORT.to_str().unwrap(),
+    );
 
     assert_eq!(response.status(), 404, "GET {path} should have been a 404");
     Ok(())
@@ -460,11 +456,9 @@ impl TestEnvironment {
         self.registry_api
             .get_or_init(|| {
                 Arc::new(
-                    RegistryApi::new(
-                        self.config().registry_api_host.clone(),
-                        self.config().crates_io_api_call_retries,
-                    )
-                    .expect("failed to initialize the registry api"),
+                    RegistryApi::new(&self.config())
+                        .expect("failed to initialize the registry api")
+                        .with_metrics(self.instance_metrics()),
                 )
             })
             .clone()
@@ -777,6 +771,18 @@ impl TestFrontend {
         self.client.request(Method::GET, url)
     }
 
+    pub(crate) fn head(&self, url: &str) -> RequestBuilder {
+        let url = self.build_url(url);
+        debug!("requesting headers of {url}");
+        self.client.request(Method::HEAD, url)
+    }
+
+    pub(crate) fn post(&self, url: &str) -> RequestBuilder {
+        let url = self.build_url(url);
+        debug!("posting to {url}");
+        self.client.request(Method::POST, url)
+    }
+
     pub(crate) fn get_no_redirect(&self, url: &str) -> RequestBuilder {
         let url = self.build_url(url);
         debug!("getting {url} (no redirects)");
@@ -31,7 +31,7 @@ use std::panic;
 use tracing::{error, warn, Span};
 pub(crate) mod sized_buffer;
 
-use std::{future::Future, thread, time::Duration};
+use std::{backtrace::BacktraceStatus, future::Future, thread, time::Duration};
 
 pub(crate) const APP_USER_AGENT: &str = concat!(
     env!("CARGO_PKG_NAME"),
@@ -40,12 +40,18 @@ pub(crate) const APP_USER_AGENT: &str = concat!(
 );
 
 pub(crate) fn report_error(err: &anyhow::Error) {
-    // Debug-format for anyhow errors includes context & backtrace
+    // The backtrace is only captured when `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`
+    // is set. We log it as separate field so the message stays readable.
+    let backtrace = err.backtrace();
+    let backtrace =
+        (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string());
+
+    // Alternate-format for anyhow errors includes the context chain
     if std::env::var("SENTRY_DSN").is_ok() {
         sentry_anyhow::capture_anyhow(err);
-        error!(reported_to_sentry = true, "{err:?}");
+        error!(reported_to_sentry = true, backtrace, "{err:#}");
     } else {
-        error!("{err:?}");
+        error!(backtrace, "{err:#}");
     }
 }
 
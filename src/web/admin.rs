@@ -0,0 +1,190 @@
+use super::{
+    cache::CachePolicy,
+    error::{AxumNope, AxumResult, JsonAxumNope, JsonSuccess},
+};
+use crate::{
+    cdn::{crate_surrogate_key, surrogate_key_purge_request},
+    web::{
+        extractors::{DbConnection, Path},
+        match_version, ReqVersion,
+    },
+    Config,
+};
+use anyhow::anyhow;
+use axum::{
+    extract::Extension,
+    response::{IntoResponse, Response as AxumResponse},
+};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use once_cell::sync::Lazy;
+use sha2::{Digest as _, Sha256};
+use std::sync::Arc;
+use tracing::info;
+
+/// Client for the CDN purge API, shared between requests so connections are reused.
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+/// Compare the token of a request with the configured one, in constant time
+/// so the comparison doesn't tell how much of the token was right.
+///
+/// We compare the SHA-256 hashes of both tokens, so the time also doesn't depend
+/// on their lengths.
+fn is_admin_token(config: &Config, token: &str) -> bool {
+    let Some(expected) = config.admin_token.as_deref() else {
+        return false;
+    };
+
+    Sha256::digest(expected)
+        .iter()
+        .zip(Sha256::digest(token).iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// Purge all CDN-cached responses about a crate, for example after a manual rebuild.
+pub(crate) async fn purge_crate_handler(
+    Path(name): Path<String>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+    mut conn: DbConnection,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<AxumResponse, JsonAxumNope> {
+    async move {
+        if !authorization.is_some_and(|TypedHeader(Authorization(bearer))| {
+            is_admin_token(&config, bearer.token())
+        }) {
+            return Err(AxumNope::Unauthorized);
+        }
+
+        let matched_release = match_version(&mut conn, &name, &ReqVersion::Latest)
+            .await?
+            .assume_exact_name()?;
+
+        let (Some(service_id), Some(api_token)) = (
+            config.cdn_purge_service_id.as_deref(),
+            config.cdn_purge_api_token.as_deref(),
+        ) else {
+            return Err(AxumNope::InternalError(anyhow!(
+                "purging the CDN is not configured"
+            )));
+        };
+
+        let keys = [crate_surrogate_key(&matched_release.name)];
+        let request = surrogate_key_purge_request(
+            &HTTP_CLIENT,
+            &config.cdn_purge_api_base,
+            service_id,
+            api_token,
+            &keys,
+        )?;
+
+        let response = HTTP_CLIENT.execute(request).await?;
+        if !response.status().is_success() {
+            return Err(AxumNope::BadGateway(anyhow!(
+                "CDN purge failed with status {}",
+                response.status()
+            )));
+        }
+        info!(name = %matched_release.name, ?keys, "purged CDN cache");
+
+        AxumResult::Ok(
+            JsonSuccess::new(
+                serde_json::json!({ "purged": keys.len() }),
+                CachePolicy::NoStore,
+            )
+            .into_response(),
+        )
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::wrapper;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn purge_crate() {
+        wrapper(|env| {
+            let mut cdn = mockito::Server::new();
+            env.override_config(|config| {
+                config.admin_token = Some("admin-secret".into());
+                config.cdn_purge_api_base = cdn.url().parse().unwrap();
+                config.cdn_purge_service_id = Some("service-id".into());
+                config.cdn_purge_api_token = Some("cdn-secret".into());
+            });
+            env.fake_release().name("foo").version("0.1.0").create()?;
+
+            let purge = cdn
+                .mock("POST", "/service/service-id/purge")
+                .match_header("fastly-key", "cdn-secret")
+                .match_header("surrogate-key", "crate:foo")
+                .with_status(200)
+                .expect(1)
+                .create();
+
+            let response = env
+                .frontend()
+                .post("/-/admin/purge/foo")
+                .bearer_auth("admin-secret")
+                .send()?;
+
+            purge.assert();
+            assert_eq!(response.status(), StatusCode::OK);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value, json!({ "result": "ok", "data": { "purged": 1 } }));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn purge_unknown_crate() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.admin_token = Some("admin-secret".into());
+            });
+
+            let response = env
+                .frontend()
+                .post("/-/admin/purge/unknown")
+                .bearer_auth("admin-secret")
+                .send()?;
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value["code"], "crate_not_found");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn purge_needs_admin_token() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.admin_token = Some("admin-secret".into());
+            });
+            env.fake_release().name("foo").version("0.1.0").create()?;
+            let web = env.frontend();
+
+            for request in [
+                web.post("/-/admin/purge/foo"),
+                web.post("/-/admin/purge/foo").bearer_auth("wrong"),
+                web.post("/-/admin/purge/foo").bearer_auth("admin-secreT"),
+            ] {
+                let response = request.send()?;
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+                assert_eq!(response.headers()["www-authenticate"], "Bearer");
+                let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+                assert_eq!(value["code"], "unauthorized");
+            }
+
+            Ok(())
+        });
+    }
+}
@@ -55,7 +55,9 @@ pub(crate) async fn build_details_handler(
     Extension(config): Extension<Arc<Config>>,
     Extension(storage): Extension<Arc<AsyncStorage>>,
 ) -> AxumResult<impl IntoResponse> {
-    let id: i32 = params.id.parse().map_err(|_| AxumNope::BuildNotFound)?;
+    let build_not_found = || AxumNope::build_not_found(&params.name, &params.version);
+
+    let id: i32 = params.id.parse().map_err(|_| build_not_found())?;
 
     let row = sqlx::query!(
         r#"SELECT
@@ -76,7 +78,7 @@ pub(crate) async fn build_details_handler(
     )
     .fetch_optional(&mut *conn)
     .await?
-    .ok_or(AxumNope::BuildNotFound)?;
+    .ok_or_else(build_not_found)?;
 
     let (output, all_log_filenames, current_filename) = if let Some(output) = row.output {
         (output, Vec::new(), None)
@@ -373,7 +375,18 @@ mod tests {
                 .get(&format!("/crate/foo/0.1.0/builds/{build_id}"))
                 .send()?;
             assert_eq!(res.status(), 404);
-            assert!(res.text()?.contains("no such build"));
+
+            let page = kuchikiki::parse_html().one(res.text()?);
+            assert!(page
+                .select_first(".description")
+                .unwrap()
+                .text_contents()
+                .contains("no such build"));
+            let link = page.select_first(".description li a").unwrap();
+            assert_eq!(
+                link.attributes.borrow().get("href").unwrap(),
+                "/crate/foo/0.1.0/builds"
+            );
 
             Ok(())
         });
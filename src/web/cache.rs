@@ -1,35 +1,54 @@
-use crate::config::Config;
+use crate::{cdn::crate_surrogate_key, config::Config};
 use axum::{
-    extract::Request as AxumHttpRequest, middleware::Next, response::Response as AxumResponse,
+    extract::{Extension, Request as AxumHttpRequest},
+    middleware::Next,
+    response::{IntoResponse, Response as AxumResponse},
 };
-use http::{header::CACHE_CONTROL, HeaderValue};
+use http::{
+    header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+    HeaderMap, HeaderName, HeaderValue, StatusCode,
+};
+use sha2::{Digest as _, Sha256};
 use std::sync::Arc;
 
+static SURROGATE_KEY: HeaderName = HeaderName::from_static("surrogate-key");
+
 pub static NO_CACHING: HeaderValue = HeaderValue::from_static("max-age=0");
 pub static SHORT: HeaderValue = HeaderValue::from_static("max-age=60");
 
 pub static NO_STORE_MUST_REVALIDATE: HeaderValue =
     HeaderValue::from_static("no-cache, no-store, must-revalidate, max-age=0");
 
+pub static NO_STORE: HeaderValue = HeaderValue::from_static("no-store, max-age=0");
+
 pub static FOREVER_IN_CDN_AND_BROWSER: HeaderValue = HeaderValue::from_static("max-age=31104000");
 
 /// defines the wanted caching behaviour for a web response.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum CachePolicy {
     /// no browser or CDN caching.
     /// In some cases the browser might still use cached content,
     /// for example when using the "back" button or when it can't
     /// connect to the server.
+    #[default]
     NoCaching,
     /// don't cache, plus
     /// * enforce revalidation
     /// * never store
     NoStoreMustRevalidate,
+    /// never store the response anywhere, neither in the CDN nor in the browser.
+    /// For server errors, and content that is specific to a single user.
+    NoStore,
     /// cache for a short time in the browser & CDN.
     /// right now: one minute.
     /// Can be used when the content can be a _little_ outdated,
     /// while protecting agains spikes in traffic.
     ShortInCdnAndBrowser,
+    /// cache for a short, configurable time in the browser & CDN.
+    /// For redirects from a version requirement like `1.2` or `~1` to the release
+    /// it currently resolves to, which changes when a matching release is published.
+    /// Redirects to pinned versions never change, they use a `Forever*` policy.
+    VersionRedirect,
     /// cache forever in browser & CDN.
     /// Valid when you have hashed / versioned filenames and every rebuild would
     /// change the filename.
@@ -52,7 +71,13 @@ impl CachePolicy {
         match *self {
             CachePolicy::NoCaching => Some(NO_CACHING.clone()),
             CachePolicy::NoStoreMustRevalidate => Some(NO_STORE_MUST_REVALIDATE.clone()),
+            CachePolicy::NoStore => Some(NO_STORE.clone()),
             CachePolicy::ShortInCdnAndBrowser => Some(SHORT.clone()),
+            CachePolicy::VersionRedirect => Some(
+                format!("max-age={}", config.cache_control_version_redirect_ttl)
+                    .parse()
+                    .unwrap(),
+            ),
             CachePolicy::ForeverInCdnAndBrowser => Some(FOREVER_IN_CDN_AND_BROWSER.clone()),
             CachePolicy::ForeverInCdn => {
                 if config.cache_invalidatable_responses {
@@ -79,6 +104,77 @@ impl CachePolicy {
     }
 }
 
+/// CDN cache tags for a response, sent as `Surrogate-Key` header.
+/// They let us purge all cached responses about a crate at once.
+/// Like `CachePolicy`, handlers set these as response extension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SurrogateKeys(Vec<String>);
+
+impl SurrogateKeys {
+    pub(crate) fn for_crate(name: &str) -> Self {
+        Self(vec![crate_surrogate_key(name)])
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn render(&self) -> Option<HeaderValue> {
+        if self.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&self.0.join(" ")).ok()
+        }
+    }
+}
+
+/// A strong `ETag` derived from the content of a response.
+///
+/// Unlike `Last-Modified` it stays the same when identical content is uploaded
+/// again, for example after a rebuild.
+pub(crate) fn content_etag(content: &[u8]) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", hex::encode(Sha256::digest(content))))
+        .expect("hex digest is a valid header value")
+}
+
+/// Answer a conditional request for a resource with the given `etag`.
+///
+/// Returns a `304 Not Modified` response when one of the entity tags in the
+/// request's `If-None-Match` header matches, or `None` when the handler should
+/// render the full response.
+/// Like for `GET` requests, the comparison is weak, so `W/"abc"` matches `"abc"`.
+pub(crate) fn not_modified(
+    request_headers: &HeaderMap,
+    etag: &HeaderValue,
+    cache_policy: CachePolicy,
+) -> Option<AxumResponse> {
+    let if_none_match = request_headers.get(IF_NONE_MATCH)?.to_str().ok()?;
+    let etag_str = etag.to_str().ok()?;
+
+    if !etag_matches(if_none_match, etag_str) {
+        return None;
+    }
+
+    Some(
+        (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag.clone())],
+            Extension(cache_policy),
+        )
+            .into_response(),
+    )
+}
+
+pub(crate) fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let weak = |tag: &str| tag.trim_start_matches("W/").to_owned();
+    let etag = weak(etag);
+
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || weak(tag) == etag)
+}
+
 pub(crate) async fn cache_middleware(req: AxumHttpRequest, next: Next) -> AxumResponse {
     let config = req
         .extensions()
@@ -105,6 +201,16 @@ pub(crate) async fn cache_middleware(req: AxumHttpRequest, next: Next) -> AxumRe
             .headers_mut()
             .insert(CACHE_CONTROL, cache_directive);
     }
+
+    if let Some(surrogate_keys) = response
+        .extensions()
+        .get::<SurrogateKeys>()
+        .and_then(SurrogateKeys::render)
+    {
+        response
+            .headers_mut()
+            .insert(SURROGATE_KEY.clone(), surrogate_keys);
+    }
     response
 }
 
@@ -119,6 +225,8 @@ mod tests {
         CachePolicy::NoStoreMustRevalidate,
         Some("no-cache, no-store, must-revalidate, max-age=0")
     )]
+    #[test_case(CachePolicy::NoStore, Some("no-store, max-age=0"))]
+    #[test_case(CachePolicy::VersionRedirect, Some("max-age=300"))]
     #[test_case(CachePolicy::ForeverInCdnAndBrowser, Some("max-age=31104000"))]
     #[test_case(CachePolicy::ForeverInCdn, None)]
     #[test_case(
@@ -135,6 +243,68 @@ mod tests {
         });
     }
 
+    #[test]
+    fn render_version_redirect_with_config() {
+        wrapper(|env| {
+            env.override_config(|config| config.cache_control_version_redirect_ttl = 30);
+            assert_eq!(
+                CachePolicy::VersionRedirect.render(&env.config()).unwrap(),
+                "max-age=30"
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn render_surrogate_keys() {
+        assert_eq!(SurrogateKeys::default().render(), None);
+        assert_eq!(
+            SurrogateKeys::for_crate("tokio").render().unwrap(),
+            "crate:tokio"
+        );
+    }
+
+    #[test_case("\"abc\""; "exact")]
+    #[test_case("W/\"abc\""; "weak")]
+    #[test_case("\"xyz\", \"abc\""; "list")]
+    #[test_case("*"; "wildcard")]
+    fn not_modified_when_etag_matches(if_none_match: &str) {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(if_none_match).unwrap());
+        let etag = HeaderValue::from_static("\"abc\"");
+
+        let response = not_modified(&headers, &etag, CachePolicy::ForeverInCdn).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], etag);
+        assert!(matches!(
+            response.extensions().get::<CachePolicy>(),
+            Some(CachePolicy::ForeverInCdn)
+        ));
+    }
+
+    #[test]
+    fn not_modified_with_other_etag() {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("\"xyz\""));
+
+        assert!(not_modified(
+            &headers,
+            &HeaderValue::from_static("\"abc\""),
+            CachePolicy::NoCaching
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn not_modified_without_if_none_match() {
+        assert!(not_modified(
+            &HeaderMap::new(),
+            &HeaderValue::from_static("\"abc\""),
+            CachePolicy::NoCaching
+        )
+        .is_none());
+    }
+
     #[test]
     fn render_stale_without_config() {
         wrapper(|env| {
@@ -0,0 +1,231 @@
+//! Redirects for former names and common typos of crates.
+
+use super::{
+    cache::CachePolicy,
+    error::{AxumNope, AxumResult},
+    resolve_crate_name,
+    routes::INTERNAL_PREFIXES,
+    CrateNameResolution,
+};
+use crate::{db::Pool, Config};
+use anyhow::Context as _;
+use axum::{
+    extract::Request as AxumHttpRequest,
+    http::Uri,
+    middleware::Next,
+    response::{IntoResponse, Response as AxumResponse},
+};
+use std::sync::Arc;
+
+/// Where the crate name is in a request path, as index of the `/`-separated
+/// segments: `/crate/:name/...` or the rustdoc pages at `/:name/...`.
+fn crate_name_segment(path: &str) -> Option<(usize, &str)> {
+    let mut segments = path.split('/').enumerate().skip(1);
+    let (index, first) = segments.next()?;
+
+    if first == "crate" {
+        segments.next()
+    } else if first.is_empty() || INTERNAL_PREFIXES.contains(&first) {
+        None
+    } else {
+        Some((index, first))
+    }
+}
+
+/// The path of the request, and its query, with the crate name replaced.
+fn replace_crate_name(uri: &Uri, index: usize, name: &str) -> String {
+    let mut segments: Vec<_> = uri.path().split('/').collect();
+    segments[index] = name;
+
+    let mut target = segments.join("/");
+    if let Some(query) = uri.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    target
+}
+
+/// The redirect for an alias from `Config::crate_name_aliases`, when there is
+/// no crate with the alias as name and the crate it stands for exists.
+async fn alias_redirect(
+    pool: Pool,
+    uri: &Uri,
+    index: usize,
+    alias: &str,
+    name: &str,
+) -> AxumResult<Option<AxumNope>> {
+    let mut conn = pool.get_async().await?;
+
+    let alias_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(
+             SELECT 1 FROM crates WHERE normalize_crate_name(name) = normalize_crate_name($1)
+         )",
+    )
+    .bind(alias)
+    .fetch_one(&mut *conn)
+    .await
+    .context("error checking for a crate with the alias as name")?;
+    if alias_exists {
+        return Ok(None);
+    }
+
+    let name = match resolve_crate_name(&mut conn, name).await? {
+        CrateNameResolution::Exact { .. } => name.to_owned(),
+        CrateNameResolution::Redirect { canonical, .. } => canonical,
+        CrateNameResolution::NotFound(_) => return Ok(None),
+    };
+
+    Ok(Some(AxumNope::PermanentRedirect(
+        replace_crate_name(uri, index, &name),
+        CachePolicy::ForeverInCdn,
+    )))
+}
+
+/// Permanently redirect requests for a crate alias to the crate it stands for.
+///
+/// Crates that exist are never redirected, and aliases for crates we don't
+/// know get the usual 404.
+pub(crate) async fn crate_alias_middleware(request: AxumHttpRequest, next: Next) -> AxumResponse {
+    let config = request
+        .extensions()
+        .get::<Arc<Config>>()
+        .cloned()
+        .expect("missing config extension in request");
+
+    // owned, so the request isn't borrowed while we wait for the database
+    let uri = request.uri().clone();
+    let Some((index, alias)) = crate_name_segment(uri.path()) else {
+        return next.run(request).await;
+    };
+    let Some(name) = config.crate_name_aliases.get(alias) else {
+        return next.run(request).await;
+    };
+
+    let pool = request
+        .extensions()
+        .get::<Pool>()
+        .cloned()
+        .expect("missing pool extension in request");
+
+    match alias_redirect(pool, &uri, index, alias, name).await {
+        Ok(Some(redirect)) => redirect.into_response(),
+        Ok(None) => next.run(request).await,
+        Err(err) => err.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{assert_redirect_cached_unchecked, wrapper};
+    use reqwest::StatusCode;
+    use test_case::test_case;
+
+    #[test_case("/crate/foo/1.0.0", Some((2, "foo")))]
+    #[test_case("/foo/1.0.0/foo/", Some((1, "foo")))]
+    #[test_case("/foo", Some((1, "foo")))]
+    #[test_case("/", None)]
+    #[test_case("/crate", None)]
+    #[test_case("/releases/foo", None)]
+    #[test_case("/-/static/style.css", None)]
+    fn find_crate_name_segment(path: &str, expected: Option<(usize, &str)>) {
+        assert_eq!(crate_name_segment(path), expected);
+    }
+
+    #[test]
+    fn replace_name_keeps_path_and_query() {
+        let uri: Uri = "/old/1.0.0/old/?search=x".parse().unwrap();
+        assert_eq!(
+            replace_crate_name(&uri, 1, "new"),
+            "/new/1.0.0/old/?search=x"
+        );
+    }
+
+    #[test]
+    fn alias_redirects_to_crate() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.crate_name_aliases = [("old-name".into(), "new-name".into())].into();
+            });
+            env.fake_release()
+                .name("new-name")
+                .version("1.0.0")
+                .create()?;
+            let web = env.frontend();
+
+            let response = web.get_no_redirect("/crate/old-name/1.0.0").send()?;
+            assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+
+            assert_redirect_cached_unchecked(
+                "/crate/old-name/1.0.0",
+                "/crate/new-name/1.0.0",
+                CachePolicy::ForeverInCdn,
+                web,
+                &env.config(),
+            )?;
+            assert_redirect_cached_unchecked(
+                "/old-name/latest/old_name/",
+                "/new-name/latest/old_name/",
+                CachePolicy::ForeverInCdn,
+                web,
+                &env.config(),
+            )?;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn alias_for_missing_crate_is_not_found() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.crate_name_aliases = [("old-name".into(), "new-name".into())].into();
+            });
+
+            let response = env.frontend().get("/crate/old-name/1.0.0").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn existing_crate_is_not_redirected() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.crate_name_aliases = [("old-name".into(), "new-name".into())].into();
+            });
+            env.fake_release()
+                .name("old-name")
+                .version("1.0.0")
+                .create()?;
+            env.fake_release()
+                .name("new-name")
+                .version("1.0.0")
+                .create()?;
+
+            let response = env.frontend().get("/crate/old-name/1.0.0").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unaliased_crate_is_not_found() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                config.crate_name_aliases = [("old-name".into(), "new-name".into())].into();
+            });
+            env.fake_release()
+                .name("new-name")
+                .version("1.0.0")
+                .create()?;
+
+            let response = env.frontend().get("/crate/other-name/1.0.0").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            Ok(())
+        });
+    }
+}
@@ -7,9 +7,9 @@ use crate::{
     impl_axum_webpage,
     storage::PathNotFoundError,
     web::{
-        cache::CachePolicy,
+        cache::{CachePolicy, SurrogateKeys},
         encode_url_path,
-        error::{AxumNope, AxumResult},
+        error::{AxumNope, AxumResult, NegotiatedAxumNope},
         extractors::{DbConnection, Path},
         MatchedRelease, ReqVersion,
     },
@@ -18,6 +18,7 @@ use crate::{
 use anyhow::{anyhow, Context, Result};
 use axum::{
     extract::Extension,
+    http::{header::ACCEPT, HeaderMap},
     response::{IntoResponse, Response as AxumResponse},
 };
 use chrono::{DateTime, Utc};
@@ -437,44 +438,58 @@ pub(crate) struct CrateDetailHandlerParams {
     version: Option<ReqVersion>,
 }
 
-#[tracing::instrument(skip(conn, storage))]
+#[tracing::instrument(skip(conn, storage, headers))]
 pub(crate) async fn crate_details_handler(
     Path(params): Path<CrateDetailHandlerParams>,
     Extension(storage): Extension<Arc<AsyncStorage>>,
     mut conn: DbConnection,
-) -> AxumResult<AxumResponse> {
-    let req_version = params.version.ok_or_else(|| {
-        AxumNope::Redirect(
-            format!("/crate/{}/{}", &params.name, ReqVersion::Latest),
-            CachePolicy::ForeverInCdn,
-        )
-    })?;
-
-    let matched_release = match_version(&mut conn, &params.name, &req_version)
-        .await?
-        .assume_exact_name()?
-        .into_canonical_req_version_or_else(|version| {
+    headers: HeaderMap,
+) -> Result<AxumResponse, NegotiatedAxumNope> {
+    // API clients might end up here too, so we give them JSON errors
+    // when they ask for it.
+    let accept = headers.get(ACCEPT).cloned();
+
+    async move {
+        let req_version = params.version.ok_or_else(|| {
             AxumNope::Redirect(
-                format!("/crate/{}/{}", &params.name, version),
+                format!("/crate/{}/{}", &params.name, ReqVersion::Latest),
                 CachePolicy::ForeverInCdn,
             )
         })?;
 
-    let mut details = CrateDetails::from_matched_release(&mut conn, matched_release).await?;
+        let matched_release = match_version(&mut conn, &params.name, &req_version)
+            .await?
+            .into_exactly_named_or_else(|corrected_name, req_version| {
+                // the crate name only differs in case or `-` / `_`, which can't change
+                AxumNope::PermanentRedirect(
+                    format!("/crate/{corrected_name}/{req_version}"),
+                    CachePolicy::ForeverInCdn,
+                )
+            })?
+            .into_canonical_req_version_or_else(|version| {
+                AxumNope::redirect_to_canonical(&params.name, &req_version, version)
+            })?;
 
-    match details.fetch_readme(&storage).await {
-        Ok(readme) => details.readme = readme.or(details.readme),
-        Err(e) => warn!("error fetching readme: {:?}", &e),
-    }
+        let mut details = CrateDetails::from_matched_release(&mut conn, matched_release).await?;
 
-    let mut res = CrateDetailsPage { details }.into_response();
-    res.extensions_mut()
-        .insert::<CachePolicy>(if req_version.is_latest() {
-            CachePolicy::ForeverInCdn
-        } else {
-            CachePolicy::ForeverInCdnAndStaleInBrowser
-        });
-    Ok(res.into_response())
+        match details.fetch_readme(&storage).await {
+            Ok(readme) => details.readme = readme.or(details.readme),
+            Err(e) => warn!("error fetching readme: {:?}", &e),
+        }
+
+        let mut res = CrateDetailsPage { details }.into_response();
+        res.extensions_mut()
+            .insert::<CachePolicy>(if req_version.is_latest() {
+                CachePolicy::ForeverInCdn
+            } else {
+                CachePolicy::ForeverInCdnAndStaleInBrowser
+            });
+        res.extensions_mut()
+            .insert(SurrogateKeys::for_crate(&params.name));
+        AxumResult::Ok(res.into_response())
+    }
+    .await
+    .map_err(|err| NegotiatedAxumNope(err, accept))
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -501,14 +516,16 @@ pub(crate) async fn get_all_releases(
 
     let matched_release = match_version(&mut conn, &params.name, &params.version)
         .await?
-        .into_canonical_req_version_or_else(|_| AxumNope::VersionNotFound)?;
+        .into_canonical_req_version_or_else(|version| {
+            AxumNope::version_not_found(&params.name, version.clone())
+        })?;
 
     if matched_release.build_status() != BuildStatus::Success {
         // This handler should only be used for successful builds, so then we have all rows in the
         // `releases` table filled with data.
         // If we need this view at some point for in-progress releases or failed releases, we need
         // to handle empty doc targets.
-        return Err(AxumNope::CrateNotFound);
+        return Err(AxumNope::crate_not_found(&params.name, Vec::new()));
     }
 
     let doc_targets = sqlx::query_scalar!(
@@ -520,7 +537,7 @@ pub(crate) async fn get_all_releases(
     )
     .fetch_optional(&mut *conn)
     .await?
-    .ok_or(AxumNope::CrateNotFound)?
+    .ok_or_else(|| AxumNope::crate_not_found(&params.name, Vec::new()))?
     .map(MetaData::parse_doc_targets)
     .ok_or_else(|| anyhow!("empty doc targets for successful release"))?;
 
@@ -634,7 +651,7 @@ pub(crate) async fn get_all_platforms_inner(
     )
     .fetch_optional(&mut *conn)
     .await?
-    .ok_or(AxumNope::CrateNotFound)?;
+    .ok_or_else(|| AxumNope::crate_not_found(&params.name, Vec::new()))?;
 
     if krate.doc_targets.is_none()
         || krate.default_target.is_none()
@@ -738,6 +755,7 @@ mod tests {
     use pretty_assertions::assert_eq;
     use reqwest::StatusCode;
     use std::collections::HashMap;
+    use test_case::test_case;
 
     async fn release_build_status(
         conn: &mut sqlx::PgConnection,
@@ -1053,6 +1071,18 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_surrogate_key() {
+        wrapper(|env| {
+            env.fake_release().name("foo").version("0.0.1").create()?;
+
+            let response = env.frontend().get("/crate/foo/0.0.1").send()?;
+            assert_eq!(response.headers()["surrogate-key"], "crate:foo");
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_canonical_url() {
         wrapper(|env| {
@@ -1296,6 +1326,8 @@ mod tests {
                     login: "foobar".into(),
                     avatar: "https://example.org/foobar".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
 
@@ -1320,11 +1352,15 @@ mod tests {
                     login: "foobar".into(),
                     avatar: "https://example.org/foobarv2".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .add_owner(CrateOwner {
                     login: "barfoo".into(),
                     avatar: "https://example.org/barfoo".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
 
@@ -1358,6 +1394,8 @@ mod tests {
                     login: "barfoo".into(),
                     avatar: "https://example.org/barfoo".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
 
@@ -1382,6 +1420,8 @@ mod tests {
                     login: "barfoo".into(),
                     avatar: "https://example.org/barfoov2".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
 
@@ -1873,6 +1913,52 @@ mod tests {
         });
     }
 
+    #[test_case("Serde-Json"; "mixed case")]
+    #[test_case("serde-json"; "dash")]
+    #[test_case("SERDE_JSON"; "upper case")]
+    fn test_crate_name_is_normalized(name: &str) {
+        wrapper(|env| {
+            env.fake_release()
+                .name("serde_json")
+                .version("1.0.0")
+                .create()?;
+
+            let redirect = assert_redirect_cached(
+                &format!("/crate/{name}/latest"),
+                "/crate/serde_json/latest",
+                CachePolicy::ForeverInCdn,
+                env.frontend(),
+                &env.config(),
+            )?;
+            assert_eq!(redirect.status(), StatusCode::MOVED_PERMANENTLY);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_unknown_crate_name_is_not_normalized() {
+        wrapper(|env| {
+            env.fake_release()
+                .name("serde_json")
+                .version("1.0.0")
+                .create()?;
+
+            let response = env.frontend().get("/crate/serde-jsno/latest").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let page = kuchikiki::parse_html().one(response.text()?);
+            let suggestions: Vec<_> = page
+                .select(".description li a")
+                .unwrap()
+                .map(|link| link.text_contents())
+                .collect();
+            assert_eq!(suggestions, ["serde_json"]);
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_crate_name_with_other_uri_chars() {
         wrapper(|env| {
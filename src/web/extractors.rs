@@ -52,14 +52,43 @@ impl DerefMut for DbConnection {
 }
 
 /// custom axum `Path` extractor that uses our own AxumNope::BadRequest
-/// as error response instead of a plain text "bad request"
+/// as error response instead of a plain text "bad request".
+///
+/// An unparseable `:version` gets its own error, which shows the version.
 #[allow(clippy::disallowed_types)]
 mod path_impl {
     use super::*;
+    use crate::web::ReqVersion;
+    use axum::extract::RawPathParams;
+    use serde::de::DeserializeOwned;
 
-    #[derive(FromRequestParts)]
-    #[from_request(via(axum::extract::Path), rejection(AxumNope))]
     pub(crate) struct Path<T>(pub T);
+
+    #[async_trait]
+    impl<T, S> FromRequestParts<S> for Path<T>
+    where
+        T: DeserializeOwned + Send,
+        S: Send + Sync,
+    {
+        type Rejection = AxumNope;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+                Ok(axum::extract::Path(value)) => Ok(Self(value)),
+                Err(rejection) => Err(invalid_version(parts, state)
+                    .await
+                    .unwrap_or_else(|| rejection.into())),
+            }
+        }
+    }
+
+    async fn invalid_version<S: Send + Sync>(parts: &mut Parts, state: &S) -> Option<AxumNope> {
+        let params = RawPathParams::from_request_parts(parts, state).await.ok()?;
+        params
+            .iter()
+            .find(|(key, value)| *key == "version" && value.parse::<ReqVersion>().is_err())
+            .map(|(_, value)| AxumNope::InvalidSemverReq(value.to_owned()))
+    }
 }
 
 pub(crate) use path_impl::Path;
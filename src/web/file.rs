@@ -1,6 +1,6 @@
 //! Database based file handler
 
-use super::cache::CachePolicy;
+use super::cache::{content_etag, not_modified, CachePolicy};
 use crate::{
     error::Result,
     storage::{AsyncStorage, Blob},
@@ -8,15 +8,22 @@ use crate::{
 };
 
 use axum::{
-    extract::Extension,
+    extract::{Extension, Request as AxumHttpRequest},
     http::{
-        header::{CONTENT_TYPE, LAST_MODIFIED},
-        StatusCode,
+        header::{
+            CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        },
+        HeaderMap, HeaderValue, Method, StatusCode,
     },
+    middleware::Next,
     response::{IntoResponse, Response as AxumResponse},
 };
+use chrono::{DateTime, NaiveDateTime, Utc};
 use mime::Mime;
 
+/// The format of `Last-Modified`, without the time zone, which is always UTC.
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %T";
+
 #[derive(Debug)]
 pub(crate) struct File(pub(crate) Blob);
 
@@ -54,6 +61,7 @@ impl IntoResponse for File {
                     &self.0.date_updated.format("%a, %d %b %Y %T %Z").to_string(),
                 ),
             ],
+            [(ETAG, content_etag(&self.0.content))],
             Extension(CachePolicy::ForeverInCdnAndBrowser),
             self.0.content,
         )
@@ -61,11 +69,80 @@ impl IntoResponse for File {
     }
 }
 
+/// Parse a HTTP date like `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// We also accept `UTC` as time zone, which is what we send in `Last-Modified`.
+fn parse_http_date(value: &HeaderValue) -> Option<DateTime<Utc>> {
+    let value = value.to_str().ok()?;
+    let value = value
+        .strip_suffix(" GMT")
+        .or_else(|| value.strip_suffix(" UTC"))?;
+    NaiveDateTime::parse_from_str(value, HTTP_DATE_FORMAT)
+        .ok()
+        .map(|date| date.and_utc())
+}
+
+/// Answer `GET` requests with `304 Not Modified` when the response has an
+/// `ETag` matching the `If-None-Match` header of the request, or, when the
+/// request has no `If-None-Match`, a `Last-Modified` date that is not newer
+/// than the `If-Modified-Since` date of the request.
+///
+/// All other headers, including the caching headers, are kept.
+pub(crate) async fn not_modified_middleware(req: AxumHttpRequest, next: Next) -> AxumResponse {
+    let request_headers = if req.method() == Method::GET || req.method() == Method::HEAD {
+        req.headers().clone()
+    } else {
+        HeaderMap::new()
+    };
+
+    let response = next.run(req).await;
+
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let not_modified_response = if request_headers.contains_key(IF_NONE_MATCH) {
+        // `If-Modified-Since` is ignored when the request has `If-None-Match`
+        let cache_policy = response
+            .extensions()
+            .get::<CachePolicy>()
+            .cloned()
+            .unwrap_or_default();
+        response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| not_modified(&request_headers, etag, cache_policy))
+    } else if let Some(if_modified_since) = request_headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(parse_http_date)
+    {
+        let last_modified = response
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(parse_http_date);
+        // HTTP dates only have whole seconds
+        last_modified
+            .is_some_and(|last_modified| last_modified.timestamp() <= if_modified_since.timestamp())
+            .then(|| StatusCode::NOT_MODIFIED.into_response())
+    } else {
+        None
+    };
+
+    let Some(mut not_modified_response) = not_modified_response else {
+        return response;
+    };
+
+    // keep the headers of the full response, like the rendered caching headers
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    not_modified_response.headers_mut().extend(parts.headers);
+    not_modified_response
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test::wrapper;
-    use chrono::Utc;
+    use crate::test::{assert_cache_control, wrapper};
     use http::header::CACHE_CONTROL;
 
     #[test]
@@ -96,11 +173,154 @@ mod tests {
                 resp.headers().get(LAST_MODIFIED).unwrap(),
                 &now.format("%a, %d %b %Y %T UTC").to_string(),
             );
+            assert!(resp.headers().contains_key(ETAG));
 
             Ok(())
         });
     }
 
+    #[test]
+    fn not_modified_since_last_modified() {
+        wrapper(|env| {
+            env.fake_release()
+                .name("dummy")
+                .version("0.1.0")
+                .rustdoc_file_with("asset.js", b"content")
+                .create()?;
+
+            let web = env.frontend();
+
+            let response = web.get("/dummy/0.1.0/asset.js").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let last_modified = response
+                .headers()
+                .get(LAST_MODIFIED)
+                .expect("missing Last-Modified header")
+                .clone();
+
+            let response = web
+                .get("/dummy/0.1.0/asset.js")
+                .header(IF_MODIFIED_SINCE, last_modified.clone())
+                .send()?;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_cache_control(
+                &response,
+                CachePolicy::ForeverInCdnAndBrowser,
+                &env.config(),
+            );
+            assert_eq!(response.headers().get(LAST_MODIFIED), Some(&last_modified));
+            assert!(response.text()?.is_empty());
+
+            // modified since then
+            let response = web
+                .get("/dummy/0.1.0/asset.js")
+                .header(IF_MODIFIED_SINCE, "Mon, 01 Jan 2001 00:00:00 GMT")
+                .send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.text()?, "content");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn etag_from_content_hash() {
+        wrapper(|env| {
+            env.fake_release()
+                .name("dummy")
+                .version("0.1.0")
+                .rustdoc_file_with("asset.js", b"content")
+                .rustdoc_file_with("other.js", b"other content")
+                .create()?;
+            env.fake_release()
+                .name("dummy")
+                .version("0.2.0")
+                .rustdoc_file_with("asset.js", b"content")
+                .create()?;
+
+            let web = env.frontend();
+            let etag = |path| -> Result<HeaderValue> {
+                let response = web.get(path).send()?;
+                assert_eq!(response.status(), StatusCode::OK);
+                Ok(response
+                    .headers()
+                    .get(ETAG)
+                    .expect("missing ETag header")
+                    .clone())
+            };
+
+            let first = etag("/dummy/0.1.0/asset.js")?;
+            assert_eq!(first, etag("/dummy/0.1.0/asset.js")?);
+            // identical content uploaded again
+            assert_eq!(first, etag("/dummy/0.2.0/asset.js")?);
+            assert_ne!(first, etag("/dummy/0.1.0/other.js")?);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn not_modified_if_none_match() {
+        wrapper(|env| {
+            env.fake_release()
+                .name("dummy")
+                .version("0.1.0")
+                .rustdoc_file_with("asset.js", b"content")
+                .create()?;
+
+            let web = env.frontend();
+
+            let response = web.get("/dummy/0.1.0/asset.js").send()?;
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .expect("missing ETag header")
+                .clone();
+
+            let response = web
+                .get("/dummy/0.1.0/asset.js")
+                .header(IF_NONE_MATCH, etag.clone())
+                .send()?;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_cache_control(
+                &response,
+                CachePolicy::ForeverInCdnAndBrowser,
+                &env.config(),
+            );
+            assert_eq!(response.headers().get(ETAG), Some(&etag));
+            assert!(response.text()?.is_empty());
+
+            // `If-None-Match` wins over `If-Modified-Since`
+            let response = web
+                .get("/dummy/0.1.0/asset.js")
+                .header(IF_NONE_MATCH, "\"something-else\"")
+                .header(IF_MODIFIED_SINCE, "Fri, 01 Jan 2100 00:00:00 GMT")
+                .send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.text()?, "content");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn parse_http_dates() {
+        let expected = "1994-11-06T08:49:37Z".parse::<DateTime<Utc>>().unwrap();
+        for value in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+        ] {
+            assert_eq!(
+                parse_http_date(&HeaderValue::from_static(value)),
+                Some(expected)
+            );
+        }
+        assert_eq!(
+            parse_http_date(&HeaderValue::from_static("yesterday")),
+            None
+        );
+    }
+
     #[test]
     fn test_max_size() {
         const MAX_SIZE: usize = 1024;
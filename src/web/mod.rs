@@ -10,9 +10,11 @@ use axum_extra::middleware::option_layer;
 use serde_json::Value;
 use tracing::{info, instrument};
 
+mod admin;
 mod build_details;
 mod builds;
 pub(crate) mod cache;
+mod crate_alias;
 pub(crate) mod crate_details;
 mod csp;
 pub(crate) mod error;
@@ -23,6 +25,8 @@ mod headers;
 mod highlight;
 mod markdown;
 pub(crate) mod metrics;
+mod owners;
+mod rate_limit;
 mod releases;
 mod routes;
 mod rustdoc;
@@ -30,10 +34,12 @@ mod sitemap;
 mod source;
 mod statics;
 mod status;
+mod timeline;
 
 use crate::{impl_axum_webpage, Context};
 use anyhow::Error;
 use axum::{
+    body::{Body, HttpBody},
     extract::{Extension, MatchedPath, Request as AxumRequest},
     http::StatusCode,
     middleware,
@@ -56,7 +62,11 @@ use std::{
     sync::Arc,
 };
 use tower::ServiceBuilder;
-use tower_http::{catch_panic::CatchPanicLayer, timeout::TimeoutLayer, trace::TraceLayer};
+use tower_http::{
+    catch_panic::CatchPanicLayer, limit::RequestBodyLimitLayer,
+    map_request_body::MapRequestBodyLayer, timeout::TimeoutLayer, trace::TraceLayer,
+};
+use unicode_normalization::UnicodeNormalization as _;
 use url::form_urlencoded;
 
 use self::crate_details::Release;
@@ -66,8 +76,48 @@ use self::crate_details::Release;
 const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
 const PATH: &AsciiSet = &FRAGMENT.add(b'#').add(b'?').add(b'{').add(b'}');
 
+/// Percent-encode a URL path.
+///
+/// Non-ASCII paths are NFC-normalized first, so different code point sequences
+/// for the same text end up as the same URL.
 pub(crate) fn encode_url_path(path: &str) -> String {
-    utf8_percent_encode(path, PATH).to_string()
+    if path.is_ascii() {
+        utf8_percent_encode(path, PATH).to_string()
+    } else {
+        let normalized: String = path.nfc().collect();
+        utf8_percent_encode(&normalized, PATH).to_string()
+    }
+}
+
+/// Like `encode_url_path`, but keeps an eventual query string intact,
+/// only escaping characters that are never valid in URLs.
+pub(crate) fn encode_url_path_and_query(target: &str) -> String {
+    match target.split_once('?') {
+        Some((path, query)) => format!(
+            "{}?{}",
+            encode_url_path(path),
+            utf8_percent_encode(query, FRAGMENT)
+        ),
+        None => encode_url_path(target),
+    }
+}
+
+/// Fail with `UnknownQueryParam` for the first query parameter that isn't in `known`,
+/// so API clients notice typos instead of silently getting the default.
+pub(crate) fn reject_unknown_query_params(
+    query: Option<&str>,
+    known: &[&str],
+) -> Result<(), AxumNope> {
+    let Some(query) = query else {
+        return Ok(());
+    };
+
+    match form_urlencoded::parse(query.as_bytes()).find(|(name, _)| !known.contains(&&**name)) {
+        Some((name, _)) => Err(AxumNope::UnknownQueryParam {
+            name: name.into_owned(),
+        }),
+        None => Ok(()),
+    }
 }
 
 const DEFAULT_BIND: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 3000);
@@ -113,6 +163,47 @@ impl FromStr for ReqVersion {
     }
 }
 
+/// Longest crate name we accept, like crates.io.
+const MAX_CRATE_NAME_LENGTH: usize = 64;
+
+/// A name that could belong to a crate: not empty, not too long, and only
+/// ASCII letters, digits, `-` and `_`.
+///
+/// Whether such a crate exists is another question.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
+pub(crate) struct CrateName(String);
+
+impl CrateName {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for CrateName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for CrateName {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            bail!("crate name is empty");
+        }
+        if s.len() > MAX_CRATE_NAME_LENGTH {
+            bail!("crate name is longer than {MAX_CRATE_NAME_LENGTH} characters");
+        }
+        if let Some(c) = s
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            bail!("invalid character {c:?} in crate name");
+        }
+        Ok(CrateName(s.to_owned()))
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct MatchedRelease {
     /// crate name
@@ -138,7 +229,7 @@ impl MatchedRelease {
         if self.corrected_name.is_none() {
             Ok(self)
         } else {
-            Err(AxumNope::CrateNotFound)
+            Err(AxumNope::crate_not_found(&self.name, Vec::new()))
         }
     }
 
@@ -234,6 +325,49 @@ impl MatchedRelease {
     }
 }
 
+/// What a crate name from a request refers to.
+#[derive(Debug, PartialEq, Eq)]
+enum CrateNameResolution {
+    /// a crate with exactly this name exists.
+    Exact { crate_id: i32 },
+    /// the name only differs from the crate's `canonical` name in case or in `-` / `_`.
+    Redirect { crate_id: i32, canonical: String },
+    /// there is no such crate, these are similar names we know.
+    NotFound(Vec<String>),
+}
+
+/// Look up a crate name: first exactly, then ignoring case and `-` / `_`,
+/// and when neither matches, look for similar names to suggest.
+async fn resolve_crate_name(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+) -> Result<CrateNameResolution> {
+    if let Some(crate_id) = sqlx::query_scalar("SELECT id FROM crates WHERE name = $1")
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?
+    {
+        return Ok(CrateNameResolution::Exact { crate_id });
+    }
+
+    let row = sqlx::query!(
+        "SELECT id, name
+             FROM crates
+             WHERE normalize_crate_name(name) = normalize_crate_name($1)",
+        name,
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(match row {
+        Some(row) => CrateNameResolution::Redirect {
+            crate_id: row.id,
+            canonical: row.name,
+        },
+        None => CrateNameResolution::NotFound(suggest_crate_names(conn, name).await),
+    })
+}
+
 /// Checks the database for crate releases that match the given name and version.
 ///
 /// `version` may be an exact version number or loose semver version requirement. The return value
@@ -248,22 +382,17 @@ async fn match_version(
     name: &str,
     input_version: &ReqVersion,
 ) -> Result<MatchedRelease, AxumNope> {
-    let (crate_id, corrected_name) = {
-        let row = sqlx::query!(
-            "SELECT id, name
-             FROM crates
-             WHERE normalize_crate_name(name) = normalize_crate_name($1)",
-            name,
-        )
-        .fetch_optional(&mut *conn)
+    let (crate_id, corrected_name) = match resolve_crate_name(conn, name)
         .await
         .context("error fetching crate")?
-        .ok_or(AxumNope::CrateNotFound)?;
-
-        if row.name != name {
-            (row.id, Some(row.name))
-        } else {
-            (row.id, None)
+    {
+        CrateNameResolution::Exact { crate_id } => (crate_id, None),
+        CrateNameResolution::Redirect {
+            crate_id,
+            canonical,
+        } => (crate_id, Some(canonical)),
+        CrateNameResolution::NotFound(suggestions) => {
+            return Err(AxumNope::crate_not_found(name, suggestions));
         }
     };
 
@@ -274,7 +403,8 @@ async fn match_version(
         .context("error fetching releases for crate")?;
 
     if releases.is_empty() {
-        return Err(AxumNope::CrateNotFound);
+        // we know the crate, so its releases must have been deleted.
+        return Err(AxumNope::Gone);
     }
 
     let req_semver: VersionReq = match input_version {
@@ -292,13 +422,30 @@ async fn match_version(
                 });
             }
 
+            // releases only differing in their build metadata have the same precedence,
+            // so when the request has none, we can't know which of them was meant.
+            if parsed_req_version.build.is_empty() {
+                let candidates: Vec<_> = releases
+                    .iter()
+                    .filter(|release| release.version.cmp_precedence(parsed_req_version).is_eq())
+                    .map(|release| release.version.clone())
+                    .collect();
+                if candidates.len() > 1 {
+                    return Err(AxumNope::MultipleChoices {
+                        krate: corrected_name.unwrap_or_else(|| name.to_owned()),
+                        version: input_version.to_string(),
+                        candidates,
+                    });
+                }
+            }
+
             if let Ok(version_req) = VersionReq::parse(&parsed_req_version.to_string()) {
                 // when we don't find a release with exact version,
                 // we try to interpret it as a semver requirement.
                 // A normal semver version ("1.2.3") is equivalent to a caret semver requirement.
                 version_req
             } else {
-                return Err(AxumNope::VersionNotFound);
+                return Err(version_not_found(name, input_version, &releases));
             }
         }
         ReqVersion::Latest => VersionReq::STAR,
@@ -326,24 +473,161 @@ async fn match_version(
     // When someone wants the latest release and we have only pre-releases
     // just return the latest prerelease.
     if req_semver == VersionReq::STAR {
-        return releases
-            .iter()
-            .find(flt)
-            .cloned()
-            .map(|release| MatchedRelease {
+        if let Some(release) = releases.iter().find(flt).cloned() {
+            return Ok(MatchedRelease {
                 name: name.to_owned(),
-                corrected_name: corrected_name.clone(),
+                corrected_name,
                 req_version: input_version.clone(),
                 release,
                 all_releases: releases,
-            })
-            .ok_or(AxumNope::VersionNotFound);
+            });
+        }
     }
 
     // Since we return with a CrateNotFound earlier if the db reply is empty,
     // we know that versions were returned but none satisfied the version requirement.
     // This can only happen when all versions are yanked.
-    Err(AxumNope::VersionNotFound)
+    Err(version_not_found(name, input_version, &releases))
+}
+
+/// Crate names similar to the given one, closest first, for "did you mean" suggestions
+/// when a crate wasn't found.
+///
+/// Only names sharing enough trigrams with the given one are candidates, as decided
+/// by `pg_trgm.similarity_threshold`, so short names with several typos may not
+/// find a suggestion.
+///
+/// Failing to load suggestions is not worth failing the request for, so errors are
+/// only reported.
+async fn suggest_crate_names(conn: &mut sqlx::PgConnection, name: &str) -> Vec<String> {
+    const MAX_DISTANCE: i32 = 2;
+    const MAX_SUGGESTIONS: i64 = 5;
+
+    sqlx::query_scalar(
+        "SELECT name
+         FROM (
+            SELECT
+                name,
+                levenshtein_less_equal(
+                    normalize_crate_name(name),
+                    normalize_crate_name($1),
+                    $2
+                ) AS distance
+            FROM crates
+            -- the trigram match uses an index, so we don't compute the distance
+            -- to every crate. Names of a too different length can't be close enough.
+            WHERE
+                normalize_crate_name(name) % normalize_crate_name($1) AND
+                length(name) BETWEEN length($1) - $2 AND length($1) + $2
+         ) AS candidates
+         WHERE distance <= $2
+         ORDER BY distance, name
+         LIMIT $3",
+    )
+    .bind(name)
+    .bind(MAX_DISTANCE)
+    .bind(MAX_SUGGESTIONS)
+    .fetch_all(&mut *conn)
+    .await
+    .context("error fetching crate name suggestions")
+    .unwrap_or_else(|err| {
+        report_error(&err);
+        Vec::new()
+    })
+}
+
+/// `VersionNotFound` for the given request, listing the crate's non-yanked versions,
+/// newest first. When every version is yanked, we offer the newest yanked one instead.
+fn version_not_found(name: &str, req_version: &ReqVersion, releases: &[Release]) -> AxumNope {
+    let Ok(krate) = name.parse() else {
+        return AxumNope::ResourceNotFound(None);
+    };
+
+    AxumNope::VersionNotFound {
+        krate,
+        version: req_version.clone(),
+        available_versions: releases
+            .iter()
+            .filter(|release| release.yanked == Some(false))
+            .map(|release| release.version.clone())
+            .collect(),
+        latest_yanked_version: releases
+            .iter()
+            .all(|release| release.yanked == Some(true))
+            .then(|| {
+                releases
+                    .first()
+                    .map(|release| Box::new(release.version.clone()))
+            })
+            .flatten(),
+    }
+}
+
+/// Render the bare `405 Method Not Allowed` responses of axum's method routing
+/// as our usual error, keeping the methods from their `Allow` header.
+///
+/// Our own error responses have a body and are left alone.
+async fn method_not_allowed_middleware(req: AxumRequest, next: Next) -> AxumResponse {
+    let accept = req.headers().get(http::header::ACCEPT).cloned();
+
+    let response = next.run(req).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED
+        || response.headers().contains_key(http::header::CONTENT_TYPE)
+    {
+        return response;
+    }
+
+    let allowed = response
+        .headers()
+        .get(http::header::ALLOW)
+        .and_then(|allow| allow.to_str().ok())
+        .map(|allow| {
+            allow
+                .split(',')
+                .filter_map(|method| http::Method::from_bytes(method.trim().as_bytes()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    error::NegotiatedAxumNope(AxumNope::MethodNotAllowed { allowed }, accept).into_response()
+}
+
+/// Render the `413 Payload Too Large` responses of `RequestBodyLimitLayer` as
+/// our usual error. These come from the layer itself when the `Content-Length`
+/// is too large, and from the handler when a body without `Content-Length`
+/// turns out too large while reading it.
+async fn payload_too_large_middleware(limit: usize, req: AxumRequest, next: Next) -> AxumResponse {
+    let accept = req.headers().get(http::header::ACCEPT).cloned();
+
+    let response = next.run(req).await;
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+
+    error::NegotiatedAxumNope(AxumNope::PayloadTooLarge { limit }, accept).into_response()
+}
+
+/// Answer `HEAD` requests with the status and headers the same `GET` request
+/// would get, but without a body.
+///
+/// Runs outside of the template rendering, so the `Content-Length` we keep is
+/// the one of the rendered page, error pages included.
+async fn head_request_middleware(req: AxumRequest, next: Next) -> AxumResponse {
+    if req.method() != http::Method::HEAD {
+        return next.run(req).await;
+    }
+
+    let (mut parts, body) = next.run(req).await.into_parts();
+
+    if !parts.headers.contains_key(http::header::CONTENT_LENGTH) {
+        if let Some(length) = HttpBody::size_hint(&body).exact() {
+            parts
+                .headers
+                .insert(http::header::CONTENT_LENGTH, length.into());
+        }
+    }
+
+    AxumResponse::from_parts(parts, Body::empty())
 }
 
 async fn log_timeouts_to_sentry(req: AxumRequest, next: Next) -> AxumResponse {
@@ -392,12 +676,23 @@ fn apply_middleware(
                 set_sentry_transaction_name_from_axum_route,
             ))
             .layer(CatchPanicLayer::new())
+            .layer(middleware::from_fn(head_request_middleware))
+            .layer(middleware::from_fn(error::request_id_middleware))
+            .layer(middleware::from_fn(error::language_middleware))
             .layer(option_layer(
                 config
                     .report_request_timeouts
                     .then_some(middleware::from_fn(log_timeouts_to_sentry)),
             ))
             .layer(option_layer(config.request_timeout.map(TimeoutLayer::new)))
+            .layer(middleware::from_fn(method_not_allowed_middleware))
+            .layer(middleware::from_fn({
+                let limit = config.max_request_body_size;
+                move |req: AxumRequest, next: Next| payload_too_large_middleware(limit, req, next)
+            }))
+            .layer(RequestBodyLimitLayer::new(config.max_request_body_size))
+            // our middleware below only takes axum's own body type
+            .layer(MapRequestBodyLayer::new(Body::new))
             .layer(Extension(context.pool()?))
             .layer(Extension(context.build_queue()?))
             .layer(Extension(context.service_metrics()?))
@@ -405,12 +700,18 @@ fn apply_middleware(
             .layer(Extension(context.config()?))
             .layer(Extension(context.storage()?))
             .layer(Extension(async_storage))
+            .layer(Extension(context.registry_api()?))
+            .layer(Extension(Arc::new(
+                rate_limit::CrateRateLimiter::from_config(&config),
+            )))
             .layer(option_layer(template_data.map(Extension)))
             .layer(middleware::from_fn(csp::csp_middleware))
             .layer(option_layer(has_templates.then_some(middleware::from_fn(
                 page::web_page::render_templates_middleware,
             ))))
-            .layer(middleware::from_fn(cache::cache_middleware)),
+            .layer(middleware::from_fn(file::not_modified_middleware))
+            .layer(middleware::from_fn(cache::cache_middleware))
+            .layer(middleware::from_fn(crate_alias::crate_alias_middleware)),
     ))
 }
 
@@ -708,12 +1009,27 @@ impl MetaData {
 pub(crate) struct AxumErrorPage {
     /// The title of the page
     pub title: &'static str,
+    /// What went wrong, like `crate_not_found`, the same code JSON errors have.
+    /// Lets templates style or explain each kind of error differently.
+    pub error_kind: &'static str,
     /// The error message, displayed as a description
     pub message: Cow<'static, str>,
+    /// When set, a link to search for this query is shown below the message
+    pub search_query: Option<String>,
+    /// Links shown below the message, to help users find what they were looking for
+    pub links: Vec<ErrorLink>,
+    /// For server errors, the ID under which the error was logged
+    pub error_id: Option<String>,
     #[serde(skip)]
     pub status: StatusCode,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct ErrorLink {
+    pub text: String,
+    pub href: String,
+}
+
 impl_axum_webpage! {
     AxumErrorPage = "error.html",
     status = |err| err.status,
@@ -908,7 +1224,7 @@ mod test {
     }
 
     #[test]
-    fn binary_docs_redirect_to_crate() {
+    fn binary_docs_are_not_built() {
         wrapper(|env| {
             env.fake_release()
                 .name("bat")
@@ -917,12 +1233,16 @@ mod test {
                 .create()
                 .unwrap();
             let web = env.frontend();
-            assert_redirect("/bat/0.2.0", "/crate/bat/0.2.0", web)?;
-            assert_redirect("/bat/0.2.0/i686-unknown-linux-gnu", "/crate/bat/0.2.0", web)?;
-            /* TODO: this should work (https://github.com/rust-lang/docs.rs/issues/603)
-            assert_redirect("/bat/0.2.0/i686-unknown-linux-gnu/bat", "/crate/bat/0.2.0", web)?;
-            assert_redirect("/bat/0.2.0/i686-unknown-linux-gnu/bat/", "/crate/bat/0.2.0/", web)?;
-            */
+            for path in ["/bat/0.2.0", "/bat/0.2.0/i686-unknown-linux-gnu"] {
+                let response = web.get(path).send()?;
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+                assert!(response
+                    .text()?
+                    .contains(r#"href="/crate/bat/0.2.0/builds""#));
+            }
+            // TODO: this should work for "/bat/0.2.0/i686-unknown-linux-gnu/bat" and
+            // "/bat/0.2.0/i686-unknown-linux-gnu/bat/" too
+            // (https://github.com/rust-lang/docs.rs/issues/603)
             Ok(())
         })
     }
@@ -1030,6 +1350,66 @@ mod test {
         });
     }
 
+    #[test]
+    fn resolve_crate_name_outcomes() {
+        async_wrapper(|env| async move {
+            let db = env.async_db().await;
+            let mut conn = db.async_conn().await;
+            let release_id = env
+                .async_fake_release()
+                .await
+                .name("tokio_util")
+                .version("0.1.0")
+                .create_async()
+                .await?;
+            let crate_id: i32 = sqlx::query_scalar("SELECT crate_id FROM releases WHERE id = $1")
+                .bind(release_id)
+                .fetch_one(&mut *conn)
+                .await?;
+
+            assert_eq!(
+                resolve_crate_name(&mut conn, "tokio_util").await?,
+                CrateNameResolution::Exact { crate_id }
+            );
+            for name in ["Tokio_Util", "tokio-util", "TOKIO-UTIL"] {
+                assert_eq!(
+                    resolve_crate_name(&mut conn, name).await?,
+                    CrateNameResolution::Redirect {
+                        crate_id,
+                        canonical: "tokio_util".into()
+                    }
+                );
+            }
+            assert_eq!(
+                resolve_crate_name(&mut conn, "tokio_utils").await?,
+                CrateNameResolution::NotFound(vec!["tokio_util".into()])
+            );
+            assert_eq!(
+                resolve_crate_name(&mut conn, "something-else").await?,
+                CrateNameResolution::NotFound(Vec::new())
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn crate_page_redirects_to_differently_cased_name() {
+        wrapper(|env| {
+            env.fake_release().name("tokio").version("1.0.0").create()?;
+
+            assert_redirect_cached_unchecked(
+                "/crate/Tokio/1.0.0",
+                "/crate/tokio/1.0.0",
+                cache::CachePolicy::ForeverInCdn,
+                env.frontend(),
+                &env.config(),
+            )?;
+
+            Ok(())
+        })
+    }
+
     #[test]
     // https://github.com/rust-lang/docs.rs/issues/1682
     fn prereleases_are_considered_when_others_dont_match() {
@@ -1272,10 +1652,145 @@ mod test {
         assert_eq!(req_version.to_string(), "*")
     }
 
+    #[test_case("serde")]
+    #[test_case("serde-json"; "dash")]
+    #[test_case("serde_json"; "underscore")]
+    #[test_case("Inflector2")]
+    fn test_parse_crate_name(input: &str) {
+        let name: CrateName = input.parse().unwrap();
+        assert_eq!(name.as_str(), input);
+        assert_eq!(name.to_string(), input);
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("foo bar"; "space")]
+    #[test_case("foo/bar"; "slash")]
+    #[test_case("<script>"; "html")]
+    #[test_case("caf\u{e9}"; "non ascii")]
+    fn test_parse_crate_name_invalid(input: &str) {
+        assert!(input.parse::<CrateName>().is_err());
+    }
+
+    #[test]
+    fn test_parse_crate_name_too_long() {
+        assert!("a"
+            .repeat(MAX_CRATE_NAME_LENGTH)
+            .parse::<CrateName>()
+            .is_ok());
+        assert!("a"
+            .repeat(MAX_CRATE_NAME_LENGTH + 1)
+            .parse::<CrateName>()
+            .is_err());
+    }
+
     #[test_case("/something/", "/something/")] // already valid path
     #[test_case("/something>", "/something%3E")] // something to encode
     #[test_case("/something%3E", "/something%3E")] // re-running doesn't change anything
     fn test_encode_url_path(input: &str, expected: &str) {
         assert_eq!(encode_url_path(input), expected);
     }
+
+    #[test_case("/caf\u{e9}", "/caf\u{65}\u{301}"; "accent")]
+    #[test_case("/\u{c5}ngstr\u{f6}m", "/A\u{30a}ngstro\u{308}m"; "several")]
+    fn test_encode_url_path_normalizes_unicode(composed: &str, decomposed: &str) {
+        assert_ne!(composed, decomposed);
+        assert_eq!(encode_url_path(composed), encode_url_path(decomposed));
+    }
+
+    #[test]
+    fn test_encode_url_path_composes_characters() {
+        assert_eq!(encode_url_path("/cafe\u{301}"), "/caf%C3%A9");
+    }
+
+    #[test_case("/something>", "/something%3E")] // no query
+    #[test_case("/something?a=b&c=%20", "/something?a=b&c=%20")] // query is kept
+    #[test_case("/some thing?q=a b", "/some%20thing?q=a%20b")] // invalid chars are encoded
+    #[test_case("/something?a=b?c", "/something?a=b?c")] // only the first `?` splits
+    fn test_encode_url_path_and_query(input: &str, expected: &str) {
+        assert_eq!(encode_url_path_and_query(input), expected);
+    }
+
+    #[tokio::test]
+    async fn wrong_method_is_rendered_as_method_not_allowed() {
+        let app = AxumRouter::new()
+            .route("/", axum::routing::get(|| async { "" }))
+            .layer(middleware::from_fn(method_not_allowed_middleware));
+
+        let request = http::Request::post("/")
+            .header("Accept", "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let response = tower::ServiceExt::oneshot(app, request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[http::header::ALLOW], "GET, HEAD");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["code"], "method_not_allowed");
+    }
+
+    #[tokio::test]
+    async fn oversized_body_without_content_length_is_rejected() {
+        let app = AxumRouter::new()
+            .route("/", axum::routing::post(|body: String| async move { body }))
+            .layer(RequestBodyLimitLayer::new(16))
+            .layer(middleware::from_fn(|req, next| {
+                payload_too_large_middleware(16, req, next)
+            }));
+
+        let body = Body::from_stream(futures_util::stream::iter([Ok::<_, std::io::Error>(
+            "x".repeat(32),
+        )]));
+        let request = http::Request::post("/")
+            .header("Accept", "application/json")
+            .body(body)
+            .unwrap();
+        let response = tower::ServiceExt::oneshot(app, request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["code"], "payload_too_large");
+        assert_eq!(value["limit"], 16);
+    }
+
+    #[test_case("/dummy/0.1.0/dummy/", StatusCode::OK; "doc page")]
+    #[test_case("/crate/doesnt-exist", StatusCode::NOT_FOUND; "not found")]
+    fn head_request_has_headers_of_get_without_body(path: &str, expected_status: StatusCode) {
+        wrapper(|env| {
+            env.fake_release()
+                .name("dummy")
+                .version("0.1.0")
+                .rustdoc_file("dummy/index.html")
+                .create()?;
+
+            let web = env.frontend();
+            let get = web.get(path).send()?;
+            let head = web.head(path).send()?;
+
+            assert_eq!(get.status(), expected_status);
+            assert_eq!(head.status(), expected_status);
+            for name in [
+                http::header::CACHE_CONTROL,
+                http::header::CONTENT_TYPE,
+                http::header::ETAG,
+            ] {
+                assert_eq!(get.headers().get(&name), head.headers().get(&name));
+            }
+            assert!(head.headers().contains_key(http::header::CACHE_CONTROL));
+
+            let head_content_length = head.headers()[http::header::CONTENT_LENGTH].clone();
+            assert!(head.bytes()?.is_empty());
+            assert_eq!(
+                head_content_length.to_str()?.parse::<usize>()?,
+                get.bytes()?.len()
+            );
+
+            Ok(())
+        })
+    }
 }
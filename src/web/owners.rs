@@ -0,0 +1,161 @@
+use super::{
+    cache::CachePolicy,
+    error::{AxumResult, JsonAxumNope, JsonSuccess},
+};
+use crate::{
+    registry_api::RegistryApi,
+    web::{
+        extractors::{DbConnection, Path},
+        match_version, reject_unknown_query_params, ReqVersion,
+    },
+};
+use axum::{
+    extract::{Extension, RawQuery},
+    response::{IntoResponse, Response as AxumResponse},
+};
+use std::sync::Arc;
+
+/// The owners of a crate, as the registry knows them.
+pub(crate) async fn owners_json_handler(
+    Path(name): Path<String>,
+    RawQuery(query): RawQuery,
+    mut conn: DbConnection,
+    Extension(registry_api): Extension<Arc<RegistryApi>>,
+) -> Result<AxumResponse, JsonAxumNope> {
+    async move {
+        reject_unknown_query_params(query.as_deref(), &[])?;
+
+        let matched_release = match_version(&mut conn, &name, &ReqVersion::Latest)
+            .await?
+            .assume_exact_name()?;
+
+        let data = registry_api.get_crate_data(&matched_release.name).await?;
+
+        AxumResult::Ok(
+            JsonSuccess::new(
+                serde_json::json!({ "owners": data.owners }),
+                CachePolicy::ShortInCdnAndBrowser,
+            )
+            .into_response(),
+        )
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        registry_api::DEFAULT_AVATAR,
+        test::{assert_cache_control, wrapper},
+        web::cache::CachePolicy,
+    };
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn owners() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            env.fake_release().name("foo").version("0.1.0").create()?;
+
+            let _owners = crates_io
+                .mock("GET", "/api/v1/crates/foo/owners")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "users": [
+                            {
+                                "avatar": "https://example.com/avatar.png",
+                                "kind": "user",
+                                "login": "ferris",
+                                "name": "Ferris",
+                            },
+                        ],
+                        "teams": [
+                            {
+                                "avatar": null,
+                                "kind": "team",
+                                "login": "github:rust-lang:core",
+                                "name": "Core",
+                            },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .create();
+            let _metadata = crates_io
+                .mock("GET", "/api/v1/crates/foo")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(json!({ "crate": { "name": "foo" } }).to_string())
+                .create();
+
+            let response = env.frontend().get("/crate/foo/owners.json").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+
+            assert_eq!(value["result"], "ok");
+            assert_eq!(
+                value["data"],
+                json!({
+                    "owners": [
+                        {
+                            "avatar": "https://example.com/avatar.png",
+                            "email": null,
+                            "kind": "user",
+                            "login": "ferris",
+                            "name": "Ferris",
+                        },
+                        {
+                            "avatar": DEFAULT_AVATAR,
+                            "email": null,
+                            "kind": "team",
+                            "login": "github:rust-lang:core",
+                            "name": "Core",
+                        },
+                    ],
+                })
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unknown_query_param() {
+        wrapper(|env| {
+            env.fake_release().name("foo").version("0.1.0").create()?;
+
+            let response = env.frontend().get("/crate/foo/owners.json?wut=1").send()?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+
+            assert_eq!(value["code"], "unknown_param");
+            assert_eq!(value["param"], "wut");
+            assert_eq!(value["message"], "unknown parameter: wut");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unknown_crate() {
+        wrapper(|env| {
+            let response = env.frontend().get("/crate/unknown/owners.json").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+
+            assert_eq!(value["result"], "err");
+            assert_eq!(value["code"], "crate_not_found");
+
+            Ok(())
+        });
+    }
+}
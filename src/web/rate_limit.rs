@@ -0,0 +1,211 @@
+use super::{
+    error::{AxumNope, RateLimitInfo},
+    extractors::Path,
+};
+use crate::Config;
+use axum::{
+    extract::{Extension, Request as AxumHttpRequest},
+    middleware::Next,
+    response::{IntoResponse, Response as AxumResponse},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Above this many crates we forget the buckets that are full again,
+/// they behave the same as new ones. When that isn't enough, we also forget
+/// the buckets that were used least recently, down to `KEEP_TRACKED_CRATES`,
+/// so this doesn't happen again on the next request.
+const MAX_TRACKED_CRATES: usize = 10_000;
+const KEEP_TRACKED_CRATES: usize = MAX_TRACKED_CRATES * 9 / 10;
+
+/// Crate names that only differ in case or in `-` / `_` are the same crate,
+/// and share a bucket.
+fn bucket_key(krate: &str) -> String {
+    krate.to_lowercase().replace('-', "_")
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Limits how often the expensive endpoints can be requested for a single crate,
+/// with a token bucket per crate.
+///
+/// Every request takes a token, and the bucket is refilled with `rate` tokens
+/// per second, up to `burst`. Without a configured rate nothing is limited.
+#[derive(Debug)]
+pub(crate) struct CrateRateLimiter {
+    rate: Option<f64>,
+    burst: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl CrateRateLimiter {
+    pub(crate) fn new(rate: Option<f64>, burst: u32) -> Self {
+        Self {
+            rate: rate.filter(|rate| *rate > 0.0),
+            burst,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn from_config(config: &Config) -> Self {
+        Self::new(config.crate_rate_limit, config.crate_rate_limit_burst)
+    }
+
+    /// Take a token from the bucket of `krate`, or fail with `RateLimited`
+    /// when it is empty.
+    fn check(&self, krate: &str) -> Result<(), AxumNope> {
+        let Some(rate) = self.rate else {
+            return Ok(());
+        };
+        let burst = f64::from(self.burst);
+        let now = Instant::now();
+
+        let key = bucket_key(krate);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CRATES && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst
+            });
+        }
+        if buckets.len() >= MAX_TRACKED_CRATES && !buckets.contains_key(&key) {
+            let mut by_age: Vec<_> = buckets
+                .iter()
+                .map(|(key, bucket)| (bucket.updated, key.clone()))
+                .collect();
+            by_age.sort_unstable();
+            for (_, key) in &by_age[..by_age.len() - KEEP_TRACKED_CRATES] {
+                buckets.remove(key);
+            }
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens =
+            (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(AxumNope::RateLimited {
+            retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / rate),
+            rate_limit: RateLimitInfo {
+                limit: Some(u64::from(self.burst)),
+                remaining: Some(0),
+                reset: Some(Duration::from_secs_f64((burst - bucket.tokens) / rate)),
+            },
+        })
+    }
+}
+
+/// Rate limit requests per crate, for routes with a `:name` path parameter.
+pub(crate) async fn crate_rate_limit_middleware(
+    Path(params): Path<HashMap<String, String>>,
+    Extension(limiter): Extension<Arc<CrateRateLimiter>>,
+    request: AxumHttpRequest,
+    next: Next,
+) -> AxumResponse {
+    if let Some(name) = params.get("name") {
+        if let Err(err) = limiter.check(name) {
+            return err.into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::wrapper;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn without_rate_nothing_is_limited() {
+        let limiter = CrateRateLimiter::new(None, 1);
+        for _ in 0..10 {
+            assert!(limiter.check("foo").is_ok());
+        }
+    }
+
+    #[test]
+    fn empty_bucket_is_rate_limited() {
+        let limiter = CrateRateLimiter::new(Some(0.5), 2);
+        assert!(limiter.check("foo").is_ok());
+        assert!(limiter.check("foo").is_ok());
+
+        let Err(AxumNope::RateLimited {
+            retry_after,
+            rate_limit,
+        }) = limiter.check("foo")
+        else {
+            panic!("expected to be rate limited");
+        };
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(2));
+        assert_eq!(rate_limit.limit, Some(2));
+        assert_eq!(rate_limit.remaining, Some(0));
+
+        // other crates have their own bucket
+        assert!(limiter.check("bar").is_ok());
+    }
+
+    #[test]
+    fn names_are_normalized() {
+        let limiter = CrateRateLimiter::new(Some(0.001), 1);
+        assert!(limiter.check("Foo-Bar").is_ok());
+        assert!(limiter.check("foo_bar").is_err());
+    }
+
+    #[test]
+    fn tracked_crates_are_capped() {
+        let limiter = CrateRateLimiter::new(Some(0.001), 1);
+        for i in 0..MAX_TRACKED_CRATES * 2 {
+            assert!(limiter.check(&format!("crate-{i}")).is_ok());
+            assert!(limiter.buckets.lock().unwrap().len() <= MAX_TRACKED_CRATES);
+        }
+
+        // the most recently used buckets are kept
+        let last = format!("crate-{}", MAX_TRACKED_CRATES * 2 - 1);
+        assert!(limiter.check(&last).is_err());
+    }
+
+    #[test]
+    fn source_browser_is_rate_limited_per_crate() {
+        wrapper(|env| {
+            env.override_config(|config| {
+                // practically no refill during the test
+                config.crate_rate_limit = Some(0.001);
+                config.crate_rate_limit_burst = 2;
+            });
+            env.fake_release().name("foo").version("0.1.0").create()?;
+            env.fake_release().name("bar").version("0.1.0").create()?;
+
+            let web = env.frontend();
+            for _ in 0..2 {
+                let response = web.get("/crate/foo/0.1.0/source/").send()?;
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+
+            let response = web.get("/crate/foo/0.1.0/source/").send()?;
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert!(response.headers().contains_key("retry-after"));
+            assert_eq!(response.headers()["x-ratelimit-limit"], "2");
+
+            let response = web.get("/crate/bar/0.1.0/source/").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            Ok(())
+        });
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
     utils::{report_error, retry_async, spawn_blocking},
     web::{
         axum_parse_uri_with_params, axum_redirect, encode_url_path,
-        error::{AxumNope, AxumResult},
+        error::{AxumNope, AxumResult, JsonSearchNope, NegotiatedSearchNope, SearchNope},
         extractors::{DbConnection, Path},
         match_version, ReqVersion,
     },
@@ -16,18 +16,28 @@ use crate::{
 };
 use anyhow::{anyhow, bail, Context as _, Result};
 use axum::{
+    body::Body,
     extract::{Extension, Query},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap,
+    },
     response::{IntoResponse, Response as AxumResponse},
 };
 use base64::{engine::general_purpose::STANDARD as b64, Engine};
 use chrono::{DateTime, Utc};
-use futures_util::stream::TryStreamExt;
+use futures_util::{
+    future,
+    stream::{self, StreamExt, TryStreamExt},
+};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::Infallible;
 use std::str;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, warn};
 use url::form_urlencoded;
 
@@ -39,6 +49,16 @@ const RELEASES_IN_HOME: i64 = 15;
 const RELEASES_IN_RELEASES: i64 = 30;
 /// Releases in recent releases feed
 const RELEASES_IN_FEED: i64 = 150;
+/// Sort orders for search results, as supported by the crates.io API
+const SEARCH_SORT_OPTIONS: &[&str] = &[
+    "relevance",
+    "downloads",
+    "recent-downloads",
+    "recent-updates",
+    "new",
+];
+/// Maximum number of search results per page, crates.io doesn't return more
+const MAX_SEARCH_RESULTS_PER_PAGE: i64 = 100;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Release {
@@ -131,11 +151,39 @@ pub(crate) async fn get_releases(
 
 struct SearchResult {
     pub results: Vec<Release>,
+    /// how many crates the registry found on this page, including the ones
+    /// docs.rs doesn't know about.
+    pub registry_result_count: usize,
     pub executed_query: Option<String>,
     pub prev_page: Option<String>,
     pub next_page: Option<String>,
 }
 
+/// crates.io counts `recent_downloads` over this many days.
+const RECENT_DOWNLOADS_DAYS: f64 = 90.0;
+
+/// Popularity score of a crate for search ranking.
+///
+/// Downloads are weighted with an exponential decay by their age: recent
+/// downloads count as if they happened in the middle of the recent-downloads
+/// window, all older ones as if they happened right before it. So a crate that is
+/// used a lot right now ranks above one with the same total that nobody uses anymore.
+///
+/// crates.io only gives us these two counts, so the decay boils down to two
+/// weights, one for the recent and one for the older downloads, that only depend
+/// on the half-life.
+fn popularity(total_downloads: i64, recent_downloads: Option<i64>, half_life: Duration) -> f64 {
+    let half_life_days = half_life.as_secs_f64() / (24 * 60 * 60) as f64;
+    let weight = |age_days: f64| 0.5f64.powf(age_days / half_life_days);
+
+    let total = total_downloads.max(0);
+    let recent = recent_downloads.unwrap_or(0).clamp(0, total);
+    let older = total - recent;
+
+    recent as f64 * weight(RECENT_DOWNLOADS_DAYS / 2.0)
+        + older as f64 * weight(RECENT_DOWNLOADS_DAYS)
+}
+
 /// Get the search results for a crate search query
 ///
 /// This delegates to the crates.io search API.
@@ -157,6 +205,10 @@ async fn get_search_results(
     #[derive(Deserialize, Debug)]
     struct CratesIoCrate {
         name: String,
+        #[serde(default)]
+        downloads: i64,
+        #[serde(default)]
+        recent_downloads: Option<i64>,
     }
     #[derive(Deserialize, Debug)]
     struct CratesIoMeta {
@@ -213,10 +265,29 @@ async fn get_search_results(
         bail!("got error from crates.io: {}", messages.join("\n"));
     }
 
-    let Some(crates) = response.crates else {
+    let Some(mut crates) = response.crates else {
         bail!("missing releases in crates.io response");
     };
 
+    if let Some(half_life) = config.search_popularity_half_life {
+        let sorted_by_downloads = url
+            .query_pairs()
+            .any(|(key, value)| key == "sort" && value == "downloads");
+        if sorted_by_downloads {
+            // This only re-ranks the crates on this page. crates.io still decides
+            // which crates are on which page by their total downloads, so a crate
+            // with few total but many recent downloads doesn't move up to an
+            // earlier page.
+            // `sort_by` is stable, so crates.io's order stays for equal scores
+            crates.sort_by(|a, b| {
+                let score = |krate: &CratesIoCrate| {
+                    popularity(krate.downloads, krate.recent_downloads, half_life)
+                };
+                score(b).total_cmp(&score(a))
+            });
+        }
+    }
+
     let Some(meta) = response.meta else {
         bail!("missing metadata in crates.io response");
     };
@@ -282,6 +353,7 @@ async fn get_search_results(
     .await?;
 
     Ok(SearchResult {
+        registry_result_count: names.len(),
         // start with the original names from crates.io to keep the original ranking,
         // extend with the release/build information from docs.rs
         // Crates that are not on docs.rs yet will not be returned.
@@ -434,12 +506,67 @@ pub(crate) async fn releases_failures_by_stars_handler(
     releases_handler(&mut conn, page.map(|p| p.0), ReleaseType::Failures).await
 }
 
-pub(crate) async fn owner_handler(Path(owner): Path<String>) -> AxumResult<impl IntoResponse> {
+pub(crate) async fn owner_handler(
+    Path(owner): Path<String>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let login = owner.strip_prefix('@').unwrap_or(&owner);
+
+    let is_known: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM owners WHERE login = $1)")
+        .bind(login)
+        .fetch_one(&mut *conn)
+        .await?;
+    if !is_known {
+        return Err(AxumNope::OwnerNotFound {
+            login: login.to_owned(),
+            suggestions: suggest_owner_logins(&mut conn, login).await,
+        });
+    }
+
     axum_redirect(format!(
         "https://crates.io/users/{}",
-        encode_url_path(owner.strip_prefix('@').unwrap_or(&owner))
+        encode_url_path(login)
     ))
-    .map_err(|_| AxumNope::OwnerNotFound)
+    .map_err(|_| AxumNope::OwnerNotFound {
+        login: login.to_owned(),
+        suggestions: Vec::new(),
+    })
+}
+
+/// Owner logins similar to the given one, closest first, from those sharing enough
+/// trigrams with it.
+/// Errors are only reported, since this is just a nicety on 404 pages.
+async fn suggest_owner_logins(conn: &mut sqlx::PgConnection, login: &str) -> Vec<String> {
+    const MAX_DISTANCE: i32 = 2;
+    const MAX_SUGGESTIONS: i64 = 5;
+
+    sqlx::query_scalar(
+        "SELECT login
+         FROM (
+            SELECT
+                login,
+                levenshtein_less_equal(lower(login), lower($1), $2) AS distance
+            FROM owners
+            -- the trigram match uses an index, so we don't compute the distance
+            -- to every owner. Logins of a too different length can't be close enough.
+            WHERE
+                lower(login) % lower($1) AND
+                length(login) BETWEEN length($1) - $2 AND length($1) + $2
+         ) AS candidates
+         WHERE distance <= $2
+         ORDER BY distance, login
+         LIMIT $3",
+    )
+    .bind(login)
+    .bind(MAX_DISTANCE)
+    .bind(MAX_SUGGESTIONS)
+    .fetch_all(&mut *conn)
+    .await
+    .context("error fetching owner login suggestions")
+    .unwrap_or_else(|err| {
+        report_error(&err);
+        Vec::new()
+    })
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -472,11 +599,136 @@ impl Default for Search {
     }
 }
 
+/// Read a numeric search parameter, defaulting to `default` and clamped to `1..=max`.
+fn search_page_param(
+    params: &HashMap<String, String>,
+    field: &str,
+    default: i64,
+    max: i64,
+) -> Result<i64, AxumNope> {
+    let Some(value) = params.get(field) else {
+        return Ok(default);
+    };
+
+    value
+        .parse::<i64>()
+        .map(|value| value.clamp(1, max))
+        .map_err(|_| AxumNope::ValidationError {
+            field: field.into(),
+            message: format!("\"{value}\" is not a number"),
+        })
+}
+
+/// The search parameters shared by the HTML and the JSON search.
+struct SearchParams {
+    query: String,
+    sort_by: String,
+    page: i64,
+    per_page: i64,
+}
+
+impl SearchParams {
+    fn from_params(params: &HashMap<String, String>) -> Result<Self, AxumNope> {
+        let query = params
+            .get("query")
+            .map(|q| q.to_string())
+            .unwrap_or_default();
+        let sort_by = params
+            .get("sort")
+            .map(|q| q.to_string())
+            .unwrap_or_else(|| "relevance".to_string());
+        if !SEARCH_SORT_OPTIONS.contains(&sort_by.as_str()) {
+            return Err(AxumNope::ValidationError {
+                field: "sort".into(),
+                message: format!(
+                    "unknown sort order \"{sort_by}\", expected one of: {}",
+                    SEARCH_SORT_OPTIONS.join(", ")
+                ),
+            });
+        }
+        let page = search_page_param(params, "page", 1, i64::from(u32::MAX))?;
+        let per_page = search_page_param(
+            params,
+            "per_page",
+            RELEASES_IN_RELEASES,
+            MAX_SEARCH_RESULTS_PER_PAGE,
+        )?;
+
+        Ok(Self {
+            query,
+            sort_by,
+            page,
+            per_page,
+        })
+    }
+}
+
+/// Build the query string we pass to the crates.io search API.
+///
+/// When the request continues a search via `paginate`, the sort order is taken from
+/// the encoded crates.io parameters and written back into `sort_by`.
+fn search_registry_query(
+    params: &HashMap<String, String>,
+    query: &str,
+    page: i64,
+    per_page: i64,
+    sort_by: &mut String,
+) -> Result<String, SearchNope> {
+    if let Some(paginate) = params.get("paginate") {
+        let decoded = b64.decode(paginate.as_bytes()).map_err(|e| {
+            warn!(
+                "error when decoding pagination base64 string \"{}\": {:?}",
+                paginate, e
+            );
+            SearchNope::NoResults
+        })?;
+        let query_params = String::from_utf8_lossy(&decoded);
+
+        if !query_params.starts_with('?') {
+            // sometimes we see plain bytes being passed to `paginate`.
+            // In these cases we just return `NoResults` and don't call
+            // the crates.io API.
+            // The whole point of the `paginate` design is that we don't
+            // know anything about the pagination args and crates.io can
+            // change them as they wish, so we cannot do any more checks here.
+            warn!(
+                "didn't get query args in `paginate` arguments for search: \"{}\"",
+                query_params
+            );
+            return Err(SearchNope::NoResults);
+        }
+
+        let mut p = form_urlencoded::parse(query_params.as_bytes());
+        if let Some(v) = p.find_map(|(k, v)| {
+            if &k == "sort" {
+                Some(v.to_string())
+            } else {
+                None
+            }
+        }) {
+            *sort_by = v;
+        };
+
+        Ok(query_params.into_owned())
+    } else if !query.is_empty() {
+        let query_params: String = form_urlencoded::Serializer::new(String::new())
+            .append_pair("q", query)
+            .append_pair("sort", sort_by)
+            .append_pair("per_page", &per_page.to_string())
+            .append_pair("page", &page.to_string())
+            .finish();
+
+        Ok(format!("?{}", &query_params))
+    } else {
+        Err(SearchNope::NoResults)
+    }
+}
+
 async fn redirect_to_random_crate(
     config: Arc<Config>,
     metrics: Arc<InstanceMetrics>,
     conn: &mut sqlx::PgConnection,
-) -> AxumResult<impl IntoResponse> {
+) -> Result<impl IntoResponse, SearchNope> {
     // We try to find a random crate and redirect to it.
     //
     // The query is efficient, but relies on a static factor which depends
@@ -522,7 +774,7 @@ async fn redirect_to_random_crate(
         ))?)
     } else {
         report_error(&anyhow!("found no result in random crate search"));
-        Err(AxumNope::NoResults)
+        Err(SearchNope::NoResults)
     }
 }
 
@@ -536,137 +788,167 @@ pub(crate) async fn search_handler(
     Extension(config): Extension<Arc<Config>>,
     Extension(metrics): Extension<Arc<InstanceMetrics>>,
     Query(mut params): Query<HashMap<String, String>>,
-) -> AxumResult<AxumResponse> {
-    let query = params
-        .get("query")
-        .map(|q| q.to_string())
-        .unwrap_or_else(|| "".to_string());
-    let mut sort_by = params
-        .get("sort")
-        .map(|q| q.to_string())
-        .unwrap_or_else(|| "relevance".to_string());
-    // check if I am feeling lucky button pressed and redirect user to crate page
-    // if there is a match. Also check for paths to items within crates.
-    if params.remove("i-am-feeling-lucky").is_some() || query.contains("::") {
-        // redirect to a random crate if query is empty
-        if query.is_empty() {
-            return Ok(redirect_to_random_crate(config, metrics, &mut conn)
-                .await?
-                .into_response());
-        }
-
-        let mut queries = BTreeMap::new();
-
-        let krate = match query.split_once("::") {
-            Some((krate, query)) => {
-                queries.insert("search".into(), query.into());
-                krate
+    headers: HeaderMap,
+) -> Result<AxumResponse, NegotiatedSearchNope> {
+    // API clients might end up here too, so we give them JSON errors
+    // when they ask for it.
+    let accept = headers.get(ACCEPT).cloned();
+
+    async move {
+        let SearchParams {
+            query,
+            mut sort_by,
+            page,
+            per_page,
+        } = SearchParams::from_params(&params)?;
+        // check if I am feeling lucky button pressed and redirect user to crate page
+        // if there is a match. Also check for paths to items within crates.
+        if params.remove("i-am-feeling-lucky").is_some() || query.contains("::") {
+            // redirect to a random crate if query is empty
+            if query.is_empty() {
+                return Ok(redirect_to_random_crate(config, metrics, &mut conn)
+                    .await?
+                    .into_response());
             }
-            None => &query,
-        };
 
-        // since we never pass a version into `match_version` here, we'll never get
-        // `MatchVersion::Exact`, so the distinction between `Exact` and `Semver` doesn't
-        // matter
-        if let Ok(matchver) = match_version(&mut conn, krate, &ReqVersion::Latest)
-            .await
-            .map(|matched_release| matched_release.into_exactly_named())
-        {
-            params.remove("query");
-            queries.extend(params);
-
-            let uri = if matchver.rustdoc_status() {
-                axum_parse_uri_with_params(
-                    &format!(
-                        "/{}/{}/{}/",
-                        matchver.name,
-                        matchver.version(),
-                        matchver
-                            .target_name()
-                            .expect("target name will exist when rustdoc_status is true"),
-                    ),
-                    queries,
-                )?
-            } else {
-                format!("/crate/{}/{}", matchver.name, matchver.version())
-                    .parse::<http::Uri>()
-                    .context("could not parse redirect URI")?
+            let mut queries = BTreeMap::new();
+
+            let krate = match query.split_once("::") {
+                Some((krate, query)) => {
+                    queries.insert("search".into(), query.into());
+                    krate
+                }
+                None => &query,
             };
 
-            return Ok(super::axum_redirect(uri)?.into_response());
+            // since we never pass a version into `match_version` here, we'll never get
+            // `MatchVersion::Exact`, so the distinction between `Exact` and `Semver` doesn't
+            // matter
+            if let Ok(matchver) = match_version(&mut conn, krate, &ReqVersion::Latest)
+                .await
+                .map(|matched_release| matched_release.into_exactly_named())
+            {
+                params.remove("query");
+                queries.extend(params);
+
+                let uri = if matchver.rustdoc_status() {
+                    axum_parse_uri_with_params(
+                        &format!(
+                            "/{}/{}/{}/",
+                            matchver.name,
+                            matchver.version(),
+                            matchver
+                                .target_name()
+                                .expect("target name will exist when rustdoc_status is true"),
+                        ),
+                        queries,
+                    )?
+                } else {
+                    format!("/crate/{}/{}", matchver.name, matchver.version())
+                        .parse::<http::Uri>()
+                        .context("could not parse redirect URI")?
+                };
+
+                return Ok(super::axum_redirect(uri)?.into_response());
+            }
         }
-    }
 
-    let search_result = if let Some(paginate) = params.get("paginate") {
-        let decoded = b64.decode(paginate.as_bytes()).map_err(|e| {
-            warn!(
-                "error when decoding pagination base64 string \"{}\": {:?}",
-                paginate, e
-            );
-            AxumNope::NoResults
-        })?;
-        let query_params = String::from_utf8_lossy(&decoded);
+        let is_paginated = params.contains_key("paginate") || page > 1;
+        let registry_query = search_registry_query(&params, &query, page, per_page, &mut sort_by)?;
+        let search_result = get_search_results(&mut conn, &config, &registry_query).await?;
 
-        if !query_params.starts_with('?') {
-            // sometimes we see plain bytes being passed to `paginate`.
-            // In these cases we just return `NoResults` and don't call
-            // the crates.io API.
-            // The whole point of the `paginate` design is that we don't
-            // know anything about the pagination args and crates.io can
-            // change them as they wish, so we cannot do any more checks here.
-            warn!(
-                "didn't get query args in `paginate` arguments for search: \"{}\"",
-                query_params
-            );
-            return Err(AxumNope::NoResults);
+        // when the registry only knows a single crate for the query, go straight to it.
+        if !is_paginated
+            && search_result.registry_result_count == 1
+            && search_result.prev_page.is_none()
+            && search_result.next_page.is_none()
+        {
+            if let [release] = search_result.results.as_slice() {
+                return Err(SearchNope::Nope(AxumNope::Redirect(
+                    format!("/crate/{}/latest", release.name),
+                    CachePolicy::ShortInCdnAndBrowser,
+                )));
+            }
         }
 
-        let mut p = form_urlencoded::parse(query_params.as_bytes());
-        if let Some(v) = p.find_map(|(k, v)| {
-            if &k == "sort" {
-                Some(v.to_string())
-            } else {
-                None
-            }
-        }) {
-            sort_by = v;
-        };
+        let executed_query = search_result.executed_query.unwrap_or_default();
 
-        get_search_results(&mut conn, &config, &query_params).await?
-    } else if !query.is_empty() {
-        let query_params: String = form_urlencoded::Serializer::new(String::new())
-            .append_pair("q", &query)
-            .append_pair("sort", &sort_by)
-            .append_pair("per_page", &RELEASES_IN_RELEASES.to_string())
-            .finish();
+        let title = if search_result.results.is_empty() {
+            format!("No results found for '{executed_query}'")
+        } else {
+            format!("Search results for '{executed_query}'")
+        };
 
-        get_search_results(&mut conn, &config, &format!("?{}", &query_params)).await?
-    } else {
-        return Err(AxumNope::NoResults);
-    };
+        Result::<_, SearchNope>::Ok(
+            Search {
+                title,
+                results: search_result.results,
+                search_query: Some(executed_query),
+                search_sort_by: Some(sort_by),
+                next_page_link: search_result
+                    .next_page
+                    .map(|params| format!("/releases/search?paginate={}", b64.encode(params))),
+                previous_page_link: search_result
+                    .prev_page
+                    .map(|params| format!("/releases/search?paginate={}", b64.encode(params))),
+                ..Default::default()
+            }
+            .into_response(),
+        )
+    }
+    .await
+    .map_err(|err| NegotiatedSearchNope(err, accept))
+}
 
-    let executed_query = search_result.executed_query.unwrap_or_default();
+/// Search results as a JSON array, in the `{"result": "ok", "data": [...]}` envelope
+/// of `JsonSuccess`.
+///
+/// The array is streamed one release at a time, so API consumers paging through
+/// large result sets can start processing before the whole body arrived.
+pub(crate) async fn search_json_handler(
+    mut conn: DbConnection,
+    Extension(config): Extension<Arc<Config>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<AxumResponse, JsonSearchNope> {
+    let search_result = async move {
+        let SearchParams {
+            query,
+            mut sort_by,
+            page,
+            per_page,
+        } = SearchParams::from_params(&params)?;
+
+        let registry_query = search_registry_query(&params, &query, page, per_page, &mut sort_by)?;
+        Result::<_, SearchNope>::Ok(get_search_results(&mut conn, &config, &registry_query).await?)
+    }
+    .await
+    .map_err(JsonSearchNope)?;
+
+    // Once the first chunk is sent we can't switch to an error response anymore.
+    // A release that fails to serialize ends the array early, so the body
+    // stays valid JSON.
+    let releases = stream::iter(search_result.results)
+        .enumerate()
+        .map(|(idx, release)| {
+            serde_json::to_string(&release)
+                .map(|json| if idx == 0 { json } else { format!(",{json}") })
+                .map_err(|err| {
+                    report_error(&anyhow!(err).context("error serializing search result"));
+                })
+        })
+        .take_while(|chunk| future::ready(chunk.is_ok()))
+        .filter_map(|chunk| future::ready(chunk.ok()));
 
-    let title = if search_result.results.is_empty() {
-        format!("No results found for '{executed_query}'")
-    } else {
-        format!("Search results for '{executed_query}'")
-    };
+    let body = stream::once(future::ready(r#"{"result":"ok","data":["#.to_string()))
+        .chain(releases)
+        .chain(stream::once(future::ready("]}".to_string())))
+        .map(Ok::<_, Infallible>);
 
-    Ok(Search {
-        title,
-        results: search_result.results,
-        search_query: Some(executed_query),
-        search_sort_by: Some(sort_by),
-        next_page_link: search_result
-            .next_page
-            .map(|params| format!("/releases/search?paginate={}", b64.encode(params))),
-        previous_page_link: search_result
-            .prev_page
-            .map(|params| format!("/releases/search?paginate={}", b64.encode(params))),
-        ..Default::default()
-    }
-    .into_response())
+    Ok((
+        [(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())],
+        Body::from_stream(body),
+    )
+        .into_response())
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -954,6 +1236,35 @@ mod tests {
         })
     }
 
+    #[test]
+    fn search_rejects_unknown_sort_order() {
+        wrapper(|env| {
+            let response = env
+                .frontend()
+                .get("/releases/search?query=foo&sort=alphabetical")
+                .header("Accept", "application/json")
+                .send()?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value["code"], "validation");
+            assert_eq!(value["field"], "sort");
+            assert!(value["message"].as_str().unwrap().contains("alphabetical"));
+
+            // and a readable message for humans
+            let response = env
+                .frontend()
+                .get("/releases/search?query=foo&sort=alphabetical")
+                .send()?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert!(response
+                .text()?
+                .contains("invalid value for &quot;sort&quot;"));
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn search_result_can_retrive_sort_by_from_pagination() {
         wrapper(|env| {
@@ -1212,6 +1523,320 @@ mod tests {
         })
     }
 
+    #[test]
+    fn search_with_single_result_redirects_to_crate() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            env.fake_release().name("some_random_crate").create()?;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("q".into(), "random".into()),
+                    Matcher::UrlEncoded("per_page".into(), "30".into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [
+                            { "name": "some_random_crate" },
+                        ],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .create();
+
+            let redirect = assert_redirect(
+                "/releases/search?query=random",
+                "/crate/some_random_crate/latest",
+                env.frontend(),
+            )?;
+            assert_eq!(redirect.status(), StatusCode::FOUND);
+            assert_cache_control(&redirect, CachePolicy::ShortInCdnAndBrowser, &env.config());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn search_without_results_renders_empty_search_page() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("q".into(), "nothing".into()),
+                    Matcher::UrlEncoded("per_page".into(), "30".into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .create();
+
+            let response = env
+                .frontend()
+                .get("/releases/search?query=nothing")
+                .send()?;
+            assert!(response.status().is_success());
+            assert!(response.text()?.contains("No results found for"));
+
+            Ok(())
+        })
+    }
+
+    #[test_case("2", "10", "2", "10"; "forwarded")]
+    #[test_case("0", "0", "1", "1"; "zero")]
+    #[test_case("-3", "-5", "1", "1"; "negative")]
+    #[test_case("1", "1000", "1", "100"; "too_many_per_page")]
+    fn search_page_params(
+        page: &str,
+        per_page: &str,
+        expected_page: &str,
+        expected_per_page: &str,
+    ) {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            env.fake_release().name("some_random_crate").create()?;
+            env.fake_release().name("some_other_crate").create()?;
+
+            let m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("q".into(), "some".into()),
+                    Matcher::UrlEncoded("page".into(), expected_page.into()),
+                    Matcher::UrlEncoded("per_page".into(), expected_per_page.into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [
+                            { "name": "some_random_crate" },
+                            { "name": "some_other_crate" },
+                        ],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create();
+
+            let links = get_release_links(
+                &format!("/releases/search?query=some&page={page}&per_page={per_page}"),
+                env.frontend(),
+            )?;
+
+            m.assert();
+            assert_eq!(links.len(), 2);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn search_page_past_the_end_is_empty() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            env.fake_release().name("some_random_crate").create()?;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("q".into(), "some_random_crate".into()),
+                    Matcher::UrlEncoded("page".into(), "50".into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": "?q=some_random_crate&per_page=30&page=49",
+                        }
+                    })
+                    .to_string(),
+                )
+                .create();
+
+            let response = env
+                .frontend()
+                .get("/releases/search?query=some_random_crate&page=50")
+                .send()?;
+            assert!(response.status().is_success());
+            let page = kuchikiki::parse_html().one(response.text()?);
+            assert_eq!(page.select("a.release").unwrap().count(), 0);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn search_page_param_must_be_a_number() {
+        wrapper(|env| {
+            let response = env
+                .frontend()
+                .get("/releases/search?query=foo&per_page=many")
+                .header("Accept", "application/json")
+                .send()?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value["field"], "per_page");
+
+            Ok(())
+        })
+    }
+
+    #[test_case("relevance", &["foo", "bar", "baz"])]
+    #[test_case("downloads", &["bar", "baz", "foo"])]
+    #[test_case("recent-downloads", &["baz", "bar", "foo"])]
+    #[test_case("recent-updates", &["baz", "foo", "bar"])]
+    #[test_case("new", &["bar", "foo", "baz"])]
+    fn search_sort_order(sort: &str, expected: &[&str]) {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            for name in ["foo", "bar", "baz"] {
+                env.fake_release().name(name).create()?;
+            }
+
+            // crates.io does the sorting, we have to keep its order
+            let crates: Vec<_> = expected
+                .iter()
+                .map(|name| json!({ "name": name }))
+                .collect();
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("q".into(), "ba".into()),
+                    Matcher::UrlEncoded("sort".into(), sort.into()),
+                ]))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": crates,
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .create();
+
+            let links = get_release_links(
+                &format!("/releases/search?query=ba&sort={sort}"),
+                env.frontend(),
+            )?;
+
+            let expected: Vec<_> = expected
+                .iter()
+                .map(|name| format!("/{name}/latest/{name}/"))
+                .collect();
+            assert_eq!(links, expected);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn search_ranks_recently_popular_crates_first() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+                config.search_popularity_half_life =
+                    Some(std::time::Duration::from_secs(30 * 24 * 60 * 60));
+            });
+
+            env.fake_release().name("old_favorite").create()?;
+            env.fake_release().name("new_favorite").create()?;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::UrlEncoded("sort".into(), "downloads".into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [
+                            { "name": "old_favorite", "downloads": 1000, "recent_downloads": 10 },
+                            { "name": "new_favorite", "downloads": 1000, "recent_downloads": 900 },
+                        ],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .create();
+
+            let links = get_release_links(
+                "/releases/search?query=favorite&sort=downloads",
+                env.frontend(),
+            )?;
+
+            assert_eq!(
+                links,
+                vec![
+                    "/new_favorite/latest/new_favorite/",
+                    "/old_favorite/latest/old_favorite/",
+                ]
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn popularity_decays_older_downloads() {
+        let half_life = std::time::Duration::from_secs(30 * 24 * 60 * 60);
+
+        assert!(popularity(1000, Some(900), half_life) > popularity(1000, Some(10), half_life));
+        // without recent downloads only the decayed total counts
+        assert!(popularity(1000, None, half_life) < 1000.0);
+        // the registry's numbers don't always add up
+        assert_eq!(
+            popularity(100, Some(200), half_life),
+            popularity(100, Some(100), half_life)
+        );
+    }
+
     #[test]
     fn search_lucky_with_unknown_crate() {
         wrapper(|env| {
@@ -1259,6 +1884,86 @@ mod tests {
         })
     }
 
+    #[test]
+    fn search_json_streams_same_results_as_search() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            env.fake_release().name("some_random_crate").create()?;
+            env.fake_release().name("some_other_crate").create()?;
+            env.fake_release().name("yet_another_crate").create()?;
+
+            let _m = crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::UrlEncoded("q".into(), "some".into()))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [
+                            { "name": "some_random_crate" },
+                            { "name": "unknown_to_docs_rs" },
+                            { "name": "some_other_crate" },
+                            { "name": "yet_another_crate" },
+                        ],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(2)
+                .create();
+
+            let links = get_release_links("/releases/search?query=some", env.frontend())?;
+
+            let response = env
+                .frontend()
+                .get("/releases/search.json?query=some")
+                .send()?;
+            assert!(response.status().is_success());
+            assert_eq!(response.headers()["content-type"], "application/json");
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value["result"], "ok");
+            let releases = value["data"].as_array().unwrap();
+
+            assert_eq!(releases.len(), links.len());
+            assert_eq!(
+                releases
+                    .iter()
+                    .map(|release| release["name"].as_str().unwrap())
+                    .collect::<Vec<_>>(),
+                vec!["some_random_crate", "some_other_crate", "yet_another_crate"],
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn search_json_errors_are_json() {
+        wrapper(|env| {
+            let response = env
+                .frontend()
+                .get("/releases/search.json?query=foo&sort=alphabetical")
+                .send()?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value["code"], "validation");
+            assert_eq!(value["field"], "sort");
+
+            let response = env.frontend().get("/releases/search.json").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+            assert_eq!(value["result"], "err");
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn search() {
         wrapper(|env| {
@@ -1678,6 +2383,8 @@ mod tests {
                     login: "foobar".into(),
                     avatar: "https://example.org/foobar".into(),
                     kind: OwnerKind::User,
+                    name: None,
+                    email: None,
                 })
                 .create()?;
 
@@ -1772,9 +2479,63 @@ mod tests {
     #[test]
     fn check_owner_releases_redirect() {
         wrapper(|env| {
+            env.fake_release()
+                .name("some_crate")
+                .add_owner(CrateOwner {
+                    login: "someone".into(),
+                    avatar: "https://example.org/someone".into(),
+                    kind: OwnerKind::User,
+                    name: None,
+                    email: None,
+                })
+                .create()?;
             let web = env.frontend();
 
             assert_redirect_unchecked("/releases/someone", "https://crates.io/users/someone", web)?;
+            assert_redirect_unchecked(
+                "/releases/@someone",
+                "https://crates.io/users/someone",
+                web,
+            )?;
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn check_unknown_owner_suggestions() {
+        wrapper(|env| {
+            for login in ["someone", "different"] {
+                env.fake_release()
+                    .name(&format!("crate_of_{login}"))
+                    .add_owner(CrateOwner {
+                        login: login.into(),
+                        avatar: format!("https://example.org/{login}"),
+                        kind: OwnerKind::User,
+                        name: None,
+                        email: None,
+                    })
+                    .create()?;
+            }
+
+            let response = env.frontend().get("/releases/sommeone").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let page = kuchikiki::parse_html().one(response.text()?);
+            let suggestions: Vec<_> = page
+                .select(".description li a")
+                .unwrap()
+                .map(|link| {
+                    (
+                        link.text_contents(),
+                        link.attributes.borrow().get("href").unwrap().to_owned(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                suggestions,
+                [("someone".to_owned(), "/releases/someone".to_owned())]
+            );
+
             Ok(())
         });
     }
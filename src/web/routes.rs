@@ -1,19 +1,21 @@
 use super::{
-    cache::CachePolicy, error::AxumNope, metrics::request_recorder, statics::build_static_router,
+    cache::CachePolicy, error::AxumNope, metrics::request_recorder,
+    rate_limit::crate_rate_limit_middleware, statics::build_static_router,
 };
 use axum::{
     extract::Request as AxumHttpRequest,
     handler::Handler as AxumHandler,
+    http::Uri,
     middleware::{self, Next},
     response::{IntoResponse, Redirect},
-    routing::{get, MethodRouter},
+    routing::{get, post, MethodRouter},
     Router as AxumRouter,
 };
 use axum_extra::routing::RouterExt;
 use std::convert::Infallible;
 use tracing::{debug, instrument};
 
-const INTERNAL_PREFIXES: &[&str] = &["-", "about", "crate", "releases", "sitemap.xml"];
+pub(super) const INTERNAL_PREFIXES: &[&str] = &["-", "about", "crate", "releases", "sitemap.xml"];
 
 #[instrument(skip_all)]
 pub(crate) fn get_static<H, T, S>(handler: H) -> MethodRouter<S, Infallible>
@@ -39,6 +41,29 @@ where
     }))
 }
 
+#[instrument(skip_all)]
+fn post_internal<H, T, S>(handler: H) -> MethodRouter<S, Infallible>
+where
+    H: AxumHandler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    post(handler).route_layer(middleware::from_fn(|request, next| async {
+        request_recorder(request, next, None).await
+    }))
+}
+
+/// like `get_internal`, for expensive endpoints that are rate limited per crate.
+#[instrument(skip_all)]
+fn get_rate_limited<H, T, S>(handler: H) -> MethodRouter<S, Infallible>
+where
+    H: AxumHandler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    get_internal(handler).route_layer(middleware::from_fn(crate_rate_limit_middleware))
+}
+
 #[instrument(skip_all)]
 fn get_rustdoc<H, T, S>(handler: H) -> MethodRouter<S, Infallible>
 where
@@ -66,7 +91,7 @@ async fn block_blacklisted_prefixes_middleware(
                 uri = ?request.uri(),
                 "blocking blacklisted prefix"
             );
-            return AxumNope::CrateNotFound.into_response();
+            return AxumNope::crate_not_found(first_component, Vec::new()).into_response();
         }
     }
 
@@ -125,6 +150,15 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/sitemap.xml",
             get_internal(super::sitemap::sitemapindex_handler),
         )
+        .route("/-/readyz", get_internal(super::status::readiness_handler))
+        .route(
+            "/-/health/registry",
+            get_internal(super::status::registry_health_handler),
+        )
+        .route(
+            "/-/admin/purge/:name",
+            post_internal(super::admin::purge_crate_handler),
+        )
         .route_with_tsr(
             "/-/sitemap/:letter/sitemap.xml",
             get_internal(super::sitemap::sitemap_handler),
@@ -200,6 +234,10 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/releases/search",
             get_internal(super::releases::search_handler),
         )
+        .route(
+            "/releases/search.json",
+            get_internal(super::releases::search_json_handler),
+        )
         .route_with_tsr(
             "/releases/queue",
             get_internal(super::releases::build_queue_handler),
@@ -216,6 +254,14 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/crate/:name/:version/status.json",
             get_internal(super::status::status_handler),
         )
+        .route(
+            "/crate/:name/owners.json",
+            get_internal(super::owners::owners_json_handler),
+        )
+        .route(
+            "/crate/:name/timeline.json",
+            get_internal(super::timeline::timeline_json_handler),
+        )
         .route_with_tsr(
             "/crate/:name/:version/builds/:id",
             get_internal(super::build_details::build_details_handler),
@@ -230,11 +276,11 @@ pub(super) fn build_axum_routes() -> AxumRouter {
         )
         .route_with_tsr(
             "/crate/:name/:version/source/",
-            get_internal(super::source::source_browser_handler),
+            get_rate_limited(super::source::source_browser_handler),
         )
         .route(
             "/crate/:name/:version/source/*path",
-            get_internal(super::source::source_browser_handler),
+            get_rate_limited(super::source::source_browser_handler),
         )
         .route(
             "/crate/:name/:version/menus/platforms/:target",
@@ -339,8 +385,8 @@ pub(super) fn build_axum_routes() -> AxumRouter {
         .fallback(fallback)
 }
 
-async fn fallback() -> impl IntoResponse {
-    AxumNope::ResourceNotFound
+async fn fallback(uri: Uri) -> impl IntoResponse {
+    AxumNope::ResourceNotFound(Some(uri.path().to_owned()))
 }
 
 #[cfg(test)]
@@ -375,7 +421,7 @@ mod tests {
         wrapper(|env| {
             let response = env.frontend().get("/-/rustdoc.static/style.css").send()?;
             assert_eq!(response.status(), StatusCode::NOT_FOUND);
-            assert_cache_control(&response, CachePolicy::NoCaching, &env.config());
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
             Ok(())
         })
     }
@@ -7,12 +7,13 @@ use crate::{
     web::{
         axum_cached_redirect, axum_parse_uri_with_params,
         cache::CachePolicy,
-        crate_details::CrateDetails,
+        crate_details::{CrateDetails, Release},
         csp::Csp,
         encode_url_path,
         error::{AxumNope, AxumResult},
         extractors::{DbConnection, Path},
         file::File,
+        headers::CanonicalUrl,
         match_version,
         page::TemplateData,
         MetaData, ReqVersion,
@@ -25,6 +26,7 @@ use axum::{
     http::{StatusCode, Uri},
     response::{Html, IntoResponse, Response as AxumResponse},
 };
+use axum_extra::headers::HeaderMapExt;
 use lol_html::errors::RewritingError;
 use once_cell::sync::Lazy;
 use semver::Version;
@@ -69,7 +71,7 @@ async fn try_serve_legacy_toolchain_asset(
     storage: Arc<AsyncStorage>,
     config: Arc<Config>,
     path: impl AsRef<str>,
-) -> AxumResult<AxumResponse> {
+) -> anyhow::Result<AxumResponse> {
     let path = path.as_ref().to_owned();
     // FIXME: this could be optimized: when a path doesn't exist
     // in storage, we don't need to recheck on every request.
@@ -79,13 +81,14 @@ async fn try_serve_legacy_toolchain_asset(
     // since new nightly versions will always put their
     // toolchain specific resources into the new folder,
     // which is reached via the new handler.
-    Ok(File::from_path(&storage, &path, &config)
+    File::from_path(&storage, &path, &config)
         .await
-        .map(IntoResponse::into_response)?)
+        .map(IntoResponse::into_response)
 }
 
 /// Handler called for `/:crate` and `/:crate/:version` URLs. Automatically redirects to the docs
-/// or crate details page based on whether the given crate version was successfully built.
+/// when the given crate version was successfully built, and shows that the docs weren't built
+/// otherwise.
 #[instrument(skip(storage, config, conn))]
 pub(crate) async fn rustdoc_redirector_handler(
     Path(params): Path<RustdocRedirectorParams>,
@@ -123,7 +126,8 @@ pub(crate) async fn rustdoc_redirector_handler(
         {
             return try_serve_legacy_toolchain_asset(storage, config, params.name)
                 .instrument(info_span!("serve static asset"))
-                .await;
+                .await
+                .map_err(|err| AxumNope::for_request(err, &uri));
         }
     }
 
@@ -186,10 +190,10 @@ pub(crate) async fn rustdoc_redirector_handler(
                 {
                     Ok(blob) => Ok(File(blob).into_response()),
                     Err(err) => {
-                        if !matches!(err.downcast_ref(), Some(AxumNope::ResourceNotFound))
+                        if !matches!(err.downcast_ref(), Some(AxumNope::ResourceNotFound(_)))
                             && !matches!(
                                 err.downcast_ref(),
-                                Some(crate::storage::PathNotFoundError)
+                                Some(crate::storage::PathNotFoundError { .. })
                             )
                         {
                             debug!(?target, ?err, "got error serving file");
@@ -200,9 +204,11 @@ pub(crate) async fn rustdoc_redirector_handler(
                         // docs that were affected by this bug.
                         // https://github.com/rust-lang/docs.rs/issues/1979
                         if target.starts_with("search-") || target.starts_with("settings-") {
-                            try_serve_legacy_toolchain_asset(storage, config, target).await
+                            try_serve_legacy_toolchain_asset(storage, config, target)
+                                .await
+                                .map_err(|err| AxumNope::for_request(err, &uri))
                         } else {
-                            Err(err.into())
+                            Err(AxumNope::for_request(err, &uri))
                         }
                     }
                 }
@@ -241,19 +247,26 @@ pub(crate) async fn rustdoc_redirector_handler(
             CachePolicy::ForeverInCdnAndStaleInBrowser
         };
 
-        Ok(redirect_to_doc(
+        let canonical_url = CanonicalUrl::from_path(if let Some(target) = target {
+            format!("/{crate_name}/latest/{target}/{target_name}/")
+        } else {
+            format!("/{crate_name}/latest/{target_name}/")
+        });
+
+        let mut response = redirect_to_doc(
             &query_pairs,
             encode_url_path(&url_str),
             cache,
             path_in_crate.as_deref(),
         )?
-        .into_response())
+        .into_response();
+        response.headers_mut().typed_insert(canonical_url);
+        Ok(response)
     } else {
-        Ok(axum_cached_redirect(
-            format!("/crate/{crate_name}/{}", matched_release.req_version),
-            CachePolicy::ForeverInCdn,
-        )?
-        .into_response())
+        Err(AxumNope::docs_not_built(
+            &crate_name,
+            matched_release.version(),
+        ))
     }
 }
 
@@ -273,6 +286,10 @@ struct RustdocPage {
     krate: CrateDetails,
     metadata: MetaData,
     current_target: String,
+    /// the same page in the latest version, or its root when the page is gone there,
+    /// sent as `Link rel=canonical` header.
+    #[serde(skip)]
+    canonical_url: CanonicalUrl,
 }
 
 impl RustdocPage {
@@ -286,6 +303,7 @@ impl RustdocPage {
         file_path: &str,
     ) -> AxumResult<AxumResponse> {
         let is_latest_url = self.is_latest_url;
+        let canonical_url = self.canonical_url.clone();
 
         // Build the page of documentation
         let mut ctx = tera::Context::from_serialize(self).context("error creating tera context")?;
@@ -307,7 +325,7 @@ impl RustdocPage {
             result => result.context("error rewriting HTML")?,
         };
 
-        Ok((
+        let mut response = (
             StatusCode::OK,
             (!is_latest_url).then_some([("X-Robots-Tag", "noindex")]),
             Extension(if is_latest_url {
@@ -317,7 +335,9 @@ impl RustdocPage {
             }),
             Html(html),
         )
-            .into_response())
+            .into_response();
+        response.headers_mut().typed_insert(canonical_url);
+        Ok(response)
     }
 }
 
@@ -413,11 +433,10 @@ pub(crate) async fn rustdoc_html_server_handler(
         })?;
 
     if !matched_release.rustdoc_status() {
-        return Ok(axum_cached_redirect(
-            format!("/crate/{}/{}", params.name, params.version),
-            CachePolicy::ForeverInCdn,
-        )?
-        .into_response());
+        return Err(AxumNope::docs_not_built(
+            &params.name,
+            matched_release.version(),
+        ));
     }
 
     let krate = CrateDetails::from_matched_release(&mut conn, matched_release).await?;
@@ -464,8 +483,11 @@ pub(crate) async fn rustdoc_html_server_handler(
     {
         Ok(file) => file,
         Err(err) => {
-            if !matches!(err.downcast_ref(), Some(AxumNope::ResourceNotFound))
-                && !matches!(err.downcast_ref(), Some(crate::storage::PathNotFoundError))
+            if !matches!(err.downcast_ref(), Some(AxumNope::ResourceNotFound(_)))
+                && !matches!(
+                    err.downcast_ref(),
+                    Some(crate::storage::PathNotFoundError { .. })
+                )
             {
                 debug!("got error serving {}: {}", storage_path, err);
             }
@@ -514,7 +536,7 @@ pub(crate) async fn rustdoc_html_server_handler(
                     )
                 }
 
-                Err(AxumNope::ResourceNotFound)
+                Err(AxumNope::ResourceNotFound(Some(uri.path().to_owned())))
             };
         }
     };
@@ -591,6 +613,26 @@ pub(crate) async fn rustdoc_html_server_handler(
         params.name, target_redirect, query_string
     );
 
+    // The canonical page is the same page in the latest version, when it exists there,
+    // and the root of the latest docs otherwise.
+    let canonical_path = req_path.join("/");
+    let canonical_url = if is_latest_version
+        || exists_in_release(
+            &mut conn,
+            &storage,
+            &params.name,
+            latest_release,
+            &canonical_path,
+        )
+        .await?
+    {
+        CanonicalUrl::from_path(format!("/{}/latest/{canonical_path}", params.name))
+    } else if let Some(target_name) = &latest_release.target_name {
+        CanonicalUrl::from_path(format!("/{}/latest/{target_name}/", params.name))
+    } else {
+        CanonicalUrl::from_path(format!("/{}/latest/", params.name))
+    };
+
     metrics
         .recently_accessed_releases
         .record(krate.crate_id, krate.release_id, target);
@@ -619,6 +661,7 @@ pub(crate) async fn rustdoc_html_server_handler(
                     metadata,
                     krate,
                     current_target,
+                    canonical_url,
                 }
                 .into_response(
                     &blob.content,
@@ -704,6 +747,34 @@ fn path_for_version(
     (path, query_params)
 }
 
+/// Whether the docs of the given release have a file at `path`.
+async fn exists_in_release(
+    conn: &mut sqlx::PgConnection,
+    storage: &AsyncStorage,
+    name: &str,
+    release: &Release,
+    path: &str,
+) -> AxumResult<bool> {
+    if release.rustdoc_status != Some(true) {
+        return Ok(false);
+    }
+
+    let matched_release = match_version(conn, name, &ReqVersion::Exact(release.version.clone()))
+        .await?
+        .assume_exact_name()?;
+    let krate = CrateDetails::from_matched_release(conn, matched_release).await?;
+
+    Ok(storage
+        .rustdoc_file_exists(
+            name,
+            &krate.version.to_string(),
+            krate.latest_build_id.unwrap_or(0),
+            path,
+            krate.archive_storage,
+        )
+        .await?)
+}
+
 #[instrument(skip_all)]
 pub(crate) async fn target_redirect_handler(
     Path((name, req_version, req_path)): Path<(String, ReqVersion, String)>,
@@ -712,7 +783,9 @@ pub(crate) async fn target_redirect_handler(
 ) -> AxumResult<impl IntoResponse> {
     let matched_release = match_version(&mut conn, &name, &req_version)
         .await?
-        .into_canonical_req_version_or_else(|_| AxumNope::VersionNotFound)?;
+        .into_canonical_req_version_or_else(|version| {
+            AxumNope::version_not_found(&name, version.clone())
+        })?;
 
     let crate_details = CrateDetails::from_matched_release(&mut conn, matched_release).await?;
 
@@ -725,7 +798,7 @@ pub(crate) async fn target_redirect_handler(
         .as_ref()
         .ok_or_else(|| {
             error!("target_redirect_handler was called with release with missing default_target");
-            AxumNope::VersionNotFound
+            AxumNope::version_not_found(&name, req_version.clone())
         })?;
 
     // We're trying to find the storage location
@@ -811,10 +884,13 @@ pub(crate) async fn download_handler(
     Extension(storage): Extension<Arc<AsyncStorage>>,
     Extension(config): Extension<Arc<Config>>,
 ) -> AxumResult<impl IntoResponse> {
-    let version = match_version(&mut conn, &name, &req_version)
+    let matched_release = match_version(&mut conn, &name, &req_version)
         .await?
-        .assume_exact_name()?
-        .into_version();
+        .assume_exact_name()?;
+    if !matched_release.rustdoc_status() {
+        return Err(AxumNope::docs_not_built(&name, matched_release.version()));
+    }
+    let version = matched_release.into_version();
 
     let archive_path = rustdoc_archive_path(&name, &version.to_string());
 
@@ -827,8 +903,11 @@ pub(crate) async fn download_handler(
     {
         Ok(is_public) => is_public,
         Err(err) => {
-            if matches!(err.downcast_ref(), Some(crate::storage::PathNotFoundError)) {
-                return Err(AxumNope::ResourceNotFound);
+            if matches!(
+                err.downcast_ref(),
+                Some(crate::storage::PathNotFoundError { .. })
+            ) {
+                return Err(AxumNope::ResourceNotFound(None));
             } else {
                 return Err(AxumNope::InternalError(err));
             }
@@ -853,10 +932,13 @@ pub(crate) async fn static_asset_handler(
     Path(path): Path<String>,
     Extension(storage): Extension<Arc<AsyncStorage>>,
     Extension(config): Extension<Arc<Config>>,
+    uri: Uri,
 ) -> AxumResult<impl IntoResponse> {
     let storage_path = format!("{RUSTDOC_STATIC_STORAGE_PREFIX}{path}");
 
-    Ok(File::from_path(&storage, &storage_path, &config).await?)
+    File::from_path(&storage, &storage_path, &config)
+        .await
+        .map_err(|err| AxumNope::for_request(err, &uri))
 }
 
 #[cfg(test)]
@@ -2360,11 +2442,15 @@ mod test {
                     login: "some-user".into(),
                     kind: OwnerKind::User,
                     avatar: "".into(),
+                    name: None,
+                    email: None,
                 })
                 .add_owner(CrateOwner {
                     login: "some-team".into(),
                     kind: OwnerKind::Team,
                     avatar: "".into(),
+                    name: None,
+                    email: None,
                 })
                 .create()?;
 
@@ -2520,14 +2606,103 @@ mod test {
         })
     }
 
+    #[test]
+    fn canonical_link_to_latest() {
+        wrapper(|env| {
+            env.fake_release()
+                .name("dummy")
+                .version("0.1.0")
+                .rustdoc_file("dummy/index.html")
+                .rustdoc_file("dummy/struct.Foo.html")
+                .rustdoc_file("dummy/struct.Removed.html")
+                .create()?;
+            env.fake_release()
+                .name("dummy")
+                .version("0.2.0")
+                .rustdoc_file("dummy/index.html")
+                .rustdoc_file("dummy/struct.Foo.html")
+                .create()?;
+
+            let web = env.frontend();
+
+            let response = web.get("/dummy/0.1.0/dummy/struct.Foo.html").send()?;
+            assert!(response.status().is_success());
+            assert_cache_control(
+                &response,
+                CachePolicy::ForeverInCdnAndStaleInBrowser,
+                &env.config(),
+            );
+            assert_eq!(
+                response.headers()["link"],
+                r#"<https://docs.rs/dummy/latest/dummy/struct.Foo.html>; rel="canonical""#
+            );
+
+            // pages that are gone in the latest version point to its root
+            let response = web.get("/dummy/0.1.0/dummy/struct.Removed.html").send()?;
+            assert!(response.status().is_success());
+            assert_eq!(
+                response.headers()["link"],
+                r#"<https://docs.rs/dummy/latest/dummy/>; rel="canonical""#
+            );
+
+            let response = web.get_no_redirect("/dummy/0.1.0").send()?;
+            assert_eq!(response.status(), StatusCode::FOUND);
+            assert_eq!(response.headers()["location"], "/dummy/0.1.0/dummy/");
+            assert_eq!(
+                response.headers()["link"],
+                r#"<https://docs.rs/dummy/latest/dummy/>; rel="canonical""#
+            );
+            Ok(())
+        })
+    }
+
     #[test]
     fn download_unknown_version_404() {
         wrapper(|env| {
             let web = env.frontend();
 
             let response = web.get("/crate/dummy/0.1.0/download").send()?;
-            assert_cache_control(&response, CachePolicy::NoCaching, &env.config());
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn download_without_docs_404() {
+        wrapper(|env| {
+            env.fake_release()
+                .name("dummy")
+                .version("0.1.0")
+                .build_result_failed()
+                .create()?;
+
+            let web = env.frontend();
+
+            let response = web.get("/crate/dummy/0.1.0/download").send()?;
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let page = kuchikiki::parse_html().one(response.text()?);
+            let container = page.select_first(".docsrs-package-container").unwrap();
+            assert_eq!(
+                container.attributes.borrow().get("data-error-kind"),
+                Some("docs_not_built")
+            );
+            assert!(page
+                .select(r#"a[href="/crate/dummy/0.1.0/builds"]"#)
+                .unwrap()
+                .next()
+                .is_some());
+
+            let response = web.get("/crate/not-dummy/0.1.0/download").send()?;
             assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let page = kuchikiki::parse_html().one(response.text()?);
+            let container = page.select_first(".docsrs-package-container").unwrap();
+            assert_eq!(
+                container.attributes.borrow().get("data-error-kind"),
+                Some("crate_not_found")
+            );
             Ok(())
         });
     }
@@ -2544,7 +2719,7 @@ mod test {
             let web = env.frontend();
 
             let response = web.get("/crate/dummy/0.1.0/download").send()?;
-            assert_cache_control(&response, CachePolicy::NoCaching, &env.config());
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
             assert_eq!(response.status(), StatusCode::NOT_FOUND);
             Ok(())
         });
@@ -2724,9 +2899,11 @@ mod test {
         })
     }
 
-    #[test_case("/something/1.2.3/some_path/", "/crate/something/1.2.3")]
-    #[test_case("/something/latest/some_path/", "/crate/something/latest")]
-    fn rustdoc_page_from_failed_build_redirects_to_crate(path: &str, expected: &str) {
+    #[test_case("/something/1.2.3/some_path/")]
+    #[test_case("/something/latest/some_path/")]
+    #[test_case("/something/1.2.3")]
+    #[test_case("/something")]
+    fn rustdoc_page_from_failed_build_is_docs_not_built(path: &str) {
         wrapper(|env| {
             env.fake_release()
                 .name("something")
@@ -2734,15 +2911,22 @@ mod test {
                 .archive_storage(true)
                 .build_result_failed()
                 .create()?;
-            let web = env.frontend();
 
-            assert_redirect_cached(
-                path,
-                expected,
-                CachePolicy::ForeverInCdn,
-                web,
-                &env.config(),
-            )?;
+            let response = env.frontend().get(path).send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
+
+            let page = kuchikiki::parse_html().one(response.text()?);
+            let container = page.select_first(".docsrs-package-container").unwrap();
+            assert_eq!(
+                container.attributes.borrow().get("data-error-kind"),
+                Some("docs_not_built")
+            );
+            assert!(page
+                .select(r#"a[href="/crate/something/1.2.3/builds"]"#)
+                .unwrap()
+                .next()
+                .is_some());
 
             Ok(())
         })
@@ -56,10 +56,10 @@ pub(crate) async fn sitemap_handler(
     mut conn: DbConnection,
 ) -> AxumResult<impl IntoResponse> {
     if letter.len() != 1 {
-        return Err(AxumNope::ResourceNotFound);
+        return Err(AxumNope::ResourceNotFound(None));
     } else if let Some(ch) = letter.chars().next() {
         if !(ch.is_ascii_lowercase()) {
-            return Err(AxumNope::ResourceNotFound);
+            return Err(AxumNope::ResourceNotFound(None));
         }
     }
 
@@ -148,7 +148,11 @@ pub(crate) async fn about_handler(subpage: Option<Path<String>>) -> AxumResult<i
                 Perhaps you are interested in <a href=\"https://github.com/rust-lang/docs.rs/tree/master/templates/core/about\">creating</a> it?";
             let page = AxumErrorPage {
                 title: "The requested page does not exist",
+                error_kind: "resource_not_found",
                 message: msg.into(),
+                search_query: None,
+                links: Vec::new(),
+                error_id: None,
                 status: StatusCode::NOT_FOUND,
             };
             return Ok(page.into_response());
@@ -80,6 +80,7 @@ pub(crate) fn build_static_router() -> AxumRouter {
 mod tests {
     use super::{STYLE_CSS, VENDORED_CSS};
     use crate::{
+        registry_api::DEFAULT_AVATAR,
         test::{assert_cache_control, wrapper},
         web::cache::CachePolicy,
     };
@@ -167,6 +168,20 @@ mod tests {
         });
     }
 
+    #[test]
+    fn default_avatar() {
+        wrapper(|env| {
+            let resp = env.frontend().get(DEFAULT_AVATAR).send()?;
+            assert!(resp.status().is_success());
+            assert_eq!(
+                resp.headers().get("Content-Type"),
+                Some(&"image/svg+xml".parse().unwrap()),
+            );
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn static_files() {
         wrapper(|env| {
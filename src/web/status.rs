@@ -1,12 +1,21 @@
-use super::{cache::CachePolicy, error::AxumNope};
-use crate::web::{
-    error::AxumResult,
-    extractors::{DbConnection, Path},
-    match_version, ReqVersion,
+use super::{
+    cache::CachePolicy,
+    error::{AxumNope, JsonAxumNope},
+};
+use crate::{
+    db::Pool,
+    registry_api::RegistryApi,
+    web::{
+        error::AxumResult,
+        extractors::{DbConnection, Path},
+        match_version, ReqVersion,
+    },
 };
 use axum::{
     extract::Extension, http::header::ACCESS_CONTROL_ALLOW_ORIGIN, response::IntoResponse, Json,
 };
+use std::sync::Arc;
+use tracing::warn;
 
 pub(crate) async fn status_handler(
     Path((name, req_version)): Path<(String, ReqVersion)>,
@@ -40,10 +49,39 @@ pub(crate) async fn status_handler(
 
             AxumResult::Ok(json.into_response())
         }
-        .await,
+        .await
+        .map_err(JsonAxumNope),
     )
 }
 
+/// Readiness probe: we can serve requests as long as we reach our database.
+///
+/// The registry API isn't part of this, most pages work without it and an outage
+/// there shouldn't take all our instances out of rotation. Its reachability is
+/// reported separately by `registry_health_handler`.
+pub(crate) async fn readiness_handler(
+    Extension(pool): Extension<Pool>,
+) -> AxumResult<impl IntoResponse> {
+    pool.get_async().await.map_err(|err| {
+        warn!(?err, "database is not reachable");
+        AxumNope::ServiceUnavailable { retry_after: None }
+    })?;
+
+    Ok((Extension(CachePolicy::NoStore), "ready"))
+}
+
+/// Health detail for the registry API, `503 Service Unavailable` when we can't reach it.
+pub(crate) async fn registry_health_handler(
+    Extension(registry_api): Extension<Arc<RegistryApi>>,
+) -> AxumResult<impl IntoResponse> {
+    registry_api.ping().await.map_err(|err| {
+        warn!(?err, "registry API is not reachable");
+        AxumNope::ServiceUnavailable { retry_after: None }
+    })?;
+
+    Ok((Extension(CachePolicy::NoStore), "ok"))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -150,6 +188,84 @@ mod tests {
         });
     }
 
+    #[test]
+    fn version_not_found_lists_available_versions() {
+        wrapper(|env| {
+            env.fake_release().name("foo").version("0.1.0").create()?;
+            env.fake_release().name("foo").version("0.2.0").create()?;
+            env.fake_release().name("foo").version("0.1.1").create()?;
+            env.fake_release()
+                .name("foo")
+                .version("0.1.2")
+                .yanked(true)
+                .create()?;
+
+            let response = env.frontend().get("/crate/foo/0.3.0/status.json").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+
+            assert_eq!(value["code"], "version_not_found");
+            assert_eq!(
+                value["available_versions"],
+                serde_json::json!(["0.2.0", "0.1.1", "0.1.0"])
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn ready_when_registry_is_down() {
+        wrapper(|env| {
+            let crates_io = mockito::Server::new();
+            let url = crates_io.url();
+            drop(crates_io);
+            env.override_config(|config| {
+                config.registry_api_host = url.parse().unwrap();
+            });
+
+            let response = env.frontend().get("/-/readyz").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_cache_control(&response, CachePolicy::NoStore, &env.config());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn registry_health() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+            let _m = crates_io.mock("HEAD", "/").with_status(200).create();
+
+            let response = env.frontend().get("/-/health/registry").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_cache_control(&response, CachePolicy::NoStore, &env.config());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn registry_health_when_registry_is_down() {
+        wrapper(|env| {
+            let crates_io = mockito::Server::new();
+            let url = crates_io.url();
+            drop(crates_io);
+            env.override_config(|config| {
+                config.registry_api_host = url.parse().unwrap();
+            });
+
+            let response = env.frontend().get("/-/health/registry").send()?;
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+            Ok(())
+        });
+    }
+
     // crate not found
     #[test_case("bar", "0.1")]
     #[test_case("bar", "0.1.0")]
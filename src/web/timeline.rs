@@ -0,0 +1,166 @@
+use super::{
+    cache::CachePolicy,
+    error::{AxumResult, JsonAxumNope, JsonSuccess},
+};
+use crate::{
+    registry_api::{RegistryApi, ReleaseData},
+    web::{
+        extractors::{DbConnection, Path},
+        match_version, ReqVersion,
+    },
+};
+use axum::{
+    extract::Extension,
+    response::{IntoResponse, Response as AxumResponse},
+};
+use semver::Version;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize)]
+struct TimelineEntry {
+    version: Version,
+    #[serde(flatten)]
+    data: ReleaseData,
+}
+
+/// The releases of a crate docs.rs knows about, oldest version first, with when
+/// they were published, whether they are yanked and how often they were downloaded.
+pub(crate) async fn timeline_json_handler(
+    Path(name): Path<String>,
+    mut conn: DbConnection,
+    Extension(registry_api): Extension<Arc<RegistryApi>>,
+) -> Result<AxumResponse, JsonAxumNope> {
+    async move {
+        let matched_release = match_version(&mut conn, &name, &ReqVersion::Latest)
+            .await?
+            .assume_exact_name()?;
+
+        let mut release_data = registry_api
+            .get_all_release_data(&matched_release.name)
+            .await?;
+
+        // versions the registry doesn't know (anymore) are left out.
+        let timeline: Vec<_> = matched_release
+            .all_releases
+            .into_iter()
+            .rev()
+            .filter_map(|release| {
+                let data = release_data.remove(&release.version)?;
+                Some(TimelineEntry {
+                    version: release.version,
+                    data,
+                })
+            })
+            .collect();
+
+        AxumResult::Ok(
+            JsonSuccess::new(timeline, CachePolicy::ShortInCdnAndBrowser).into_response(),
+        )
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test::{assert_cache_control, wrapper},
+        web::cache::CachePolicy,
+    };
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn timeline() {
+        wrapper(|env| {
+            let mut crates_io = mockito::Server::new();
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+            });
+
+            env.fake_release().name("foo").version("0.2.0").create()?;
+            env.fake_release().name("foo").version("0.1.0").create()?;
+            env.fake_release().name("foo").version("0.10.0").create()?;
+
+            let _versions = crates_io
+                .mock("GET", "/api/v1/crates/foo/versions")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "versions": [
+                            {
+                                "num": "0.10.0",
+                                "created_at": "2024-03-01T00:00:00Z",
+                                "downloads": 30,
+                            },
+                            {
+                                "num": "0.2.0",
+                                "created_at": "2024-02-01T00:00:00Z",
+                                "yanked": true,
+                                "yank_message": "broken",
+                                "downloads": 20,
+                            },
+                            {
+                                "num": "0.1.0",
+                                "created_at": "2024-01-01T00:00:00Z",
+                                "downloads": 10,
+                            },
+                        ],
+                    })
+                    .to_string(),
+                )
+                .create();
+
+            let response = env.frontend().get("/crate/foo/timeline.json").send()?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_cache_control(&response, CachePolicy::ShortInCdnAndBrowser, &env.config());
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+
+            assert_eq!(value["result"], "ok");
+            assert_eq!(
+                value["data"],
+                json!([
+                    {
+                        "version": "0.1.0",
+                        "created_at": "2024-01-01T00:00:00Z",
+                        "yanked": false,
+                        "yank_reason": null,
+                        "downloads": 10,
+                    },
+                    {
+                        "version": "0.2.0",
+                        "created_at": "2024-02-01T00:00:00Z",
+                        "yanked": true,
+                        "yank_reason": "broken",
+                        "downloads": 20,
+                    },
+                    {
+                        "version": "0.10.0",
+                        "created_at": "2024-03-01T00:00:00Z",
+                        "yanked": false,
+                        "yank_reason": null,
+                        "downloads": 30,
+                    },
+                ])
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unknown_crate() {
+        wrapper(|env| {
+            let response = env.frontend().get("/crate/unknown/timeline.json").send()?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let value: serde_json::Value = serde_json::from_str(&response.text()?)?;
+
+            assert_eq!(value["result"], "err");
+            assert_eq!(value["code"], "crate_not_found");
+
+            Ok(())
+        });
+    }
+}